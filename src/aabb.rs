@@ -25,20 +25,37 @@ impl Aabb {
             z: iz,
         }
     }
-    pub fn new(a: Point, b: Point) -> Self {
+    pub const fn new(a: Point, b: Point) -> Self {
         Self::new_with_interval(
             Interval::new(a.x.min(b.x), a.x.max(b.x)),
             Interval::new(a.y.min(b.y), a.y.max(b.y)),
             Interval::new(a.z.min(b.z), a.z.max(b.z)),
         )
     }
-    pub fn new_with_boxes(box0: &Self, box1: &Self) -> Self {
+    pub const fn new_with_boxes(box0: &Self, box1: &Self) -> Self {
         Self {
             x: Interval::new_with_interval(box0.x, box1.x),
             y: Interval::new_with_interval(box0.y, box1.y),
             z: Interval::new_with_interval(box0.z, box1.z),
         }
     }
+    /// Adjust the box so that no side is narrower than some delta, padding if necessary.
+    pub fn pad_to_minimums(self) -> Self {
+        let delta = 0.0001;
+        let pad = |i: Interval| if i.size() < delta { i.expand(delta) } else { i };
+        Self::new_with_interval(pad(self.x), pad(self.y), pad(self.z))
+    }
+    pub fn surface_area(&self) -> f32 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        2.0 * dz.mul_add(dx, dx.mul_add(dy, dy * dz))
+    }
+    pub const fn centroid(&self) -> Point {
+        Point::new(
+            f32::midpoint(self.x.min, self.x.max),
+            f32::midpoint(self.y.min, self.y.max),
+            f32::midpoint(self.z.min, self.z.max),
+        )
+    }
     pub const fn axis(&self, n: usize) -> &Interval {
         match n {
             1 => &self.y,
@@ -65,7 +82,7 @@ impl Aabb {
                 ray_t.max = t1;
             }
 
-            if ray_t.max() <= ray_t.min() {
+            if ray_t.max() < ray_t.min() {
                 return false;
             }
         }
@@ -0,0 +1,79 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::ray::Direction;
+
+/// What a ray sees when it leaves the scene without hitting anything.
+#[derive(Debug, Clone)]
+pub enum Background {
+    Solid(Color),
+    Gradient { bottom: Color, top: Color },
+    Environment(EnvironmentMap),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::gradient(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background {
+    pub const fn solid(c: Color) -> Self {
+        Self::Solid(c)
+    }
+    pub const fn gradient(bottom: Color, top: Color) -> Self {
+        Self::Gradient { bottom, top }
+    }
+    pub const fn environment(image: Image, rotation: f32, intensity: f32) -> Self {
+        Self::Environment(EnvironmentMap::new(image, rotation, intensity))
+    }
+
+    pub fn value(&self, direction: Direction) -> Color {
+        match self {
+            Self::Solid(c) => *c,
+            Self::Gradient { bottom, top } => {
+                let unit_direction = direction.normalize();
+                let a = 0.5 * (unit_direction.y + 1.0);
+                *bottom * (1.0 - a) + *top * a
+            }
+            Self::Environment(map) => map.value(direction),
+        }
+    }
+}
+
+/// An equirectangular (latitude/longitude) image wrapped around the scene.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: Image,
+    rotation: f32, // Turn about the vertical axis, in radians
+    intensity: f32,
+}
+
+impl EnvironmentMap {
+    pub const fn new(image: Image, rotation_degrees: f32, intensity: f32) -> Self {
+        Self {
+            image,
+            rotation: rotation_degrees.to_radians(),
+            intensity,
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn value(&self, direction: Direction) -> Color {
+        let d = direction.normalize();
+        // Same parameterization as sphere UVs: u goes around from -x, v runs from -y up to +y
+        let phi = (f32::atan2(-d.z, d.x) + PI + self.rotation).rem_euclid(TAU);
+        let theta = f32::acos(d.y.clamp(-1.0, 1.0));
+
+        let width = self.image.width();
+        let height = self.image.height();
+        let x = ((phi / TAU * width as f32) as usize).min(width - 1);
+        let y = ((theta / PI * height as f32) as usize).min(height - 1);
+        self.image.get(x, y) * self.intensity
+    }
+}
@@ -0,0 +1,65 @@
+//! `--bench-bvh`: builds each benchmark scene with both BVH builders and
+//! renders it with each, so the traversal speedup of the SAH tree shows up
+//! in the render times.
+
+use std::time::{Duration, Instant};
+
+use crate::cli::Options;
+use crate::color::Color;
+use crate::hittable::HittableObject;
+use crate::material::Material;
+use crate::random::Sampler;
+use crate::scene;
+
+pub fn bvh(options: &Options) {
+    let mut rng = Sampler::new(options.seed);
+
+    let spheres = scene::random_spheres_world(&mut rng).objects;
+    let mut torus = scene::torus_mesh(1.5, 0.5, 512, 256);
+    torus
+        .materials
+        .push(Material::metal(Color::new(0.8, 0.6, 0.2), 0.1));
+    let cases = [
+        ("random-spheres", spheres),
+        ("torus", HittableObject::mesh_triangles(torus)),
+    ];
+
+    println!(
+        "{:<16} {:>9} {:>12} {:>12} {:>12} {:>9}",
+        "scene", "objects", "builder", "build ms", "render ms", "speedup"
+    );
+    for (name, objects) in cases {
+        let mut camera = scene::find(name).expect("benchmark scene exists")(&mut rng).camera;
+        camera.image_width = 200;
+        camera.samples_per_pixel = 4;
+        options.apply(&mut camera);
+
+        let count = objects.len();
+        let mut render_times = Vec::new();
+        for builder in ["median", "sah"] {
+            let objects = objects.clone();
+            let start = Instant::now();
+            let world = match builder {
+                "median" => HittableObject::random_median_bvh(objects, &mut rng),
+                _ => HittableObject::bvh(objects),
+            };
+            let build_time = start.elapsed();
+
+            let start = Instant::now();
+            camera.render(&world);
+            let render_time = start.elapsed();
+            render_times.push(render_time);
+
+            let speedup = render_times[0].as_secs_f64() / render_time.as_secs_f64();
+            println!(
+                "{name:<16} {count:>9} {builder:>12} {:>12.1} {:>12.1} {speedup:>8.2}x",
+                milliseconds(build_time),
+                milliseconds(render_time),
+            );
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
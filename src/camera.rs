@@ -1,26 +1,47 @@
 // use std::intrinsics::mir::Discriminant;
 // use std::sync::PoisonError;
 
-use crate::color::{self, Color};
-use crate::hittable::{HitRecord, Hittable, HittableObject};
-use crate::interval::Interval;
-use crate::material::Scatter;
-use crate::random;
+use crate::background::Background;
+use crate::color::Color;
+use crate::hittable::{HittableObject, LightList};
+use crate::image::Image;
+use crate::integrator::{Integrator, PathLength, World};
+use crate::random::{self, Sampler};
 use crate::ray::{Direction, Point, Ray};
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
 use indicatif::ProgressBar;
 
+// Width and height of the square blocks of pixels handed to each worker
+const TILE_SIZE: u16 = 16;
+
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x0: u16,
+    y0: u16,
+    x1: u16,
+    y1: u16,
+}
+
 pub struct Camera {
     pub aspect_ratio: f32,
     pub image_width: u16,
     pub samples_per_pixel: u16,
+    pub integrator: Integrator,
     pub max_depth: u16,
-    pub vfov: f32, // Vertical view angle
+    pub roulette_depth: u16, // Bounces before Russian roulette may end a path
+    pub vfov: f32,           // Vertical view angle
     pub look_from: Point,
     pub look_at: Point,
     pub vup: Direction,
     pub defocus_angle: f32,
     pub focus_dist: f32,
+    pub threads: usize, // Worker threads, 0 uses every available core
+    pub seed: u64,
+    pub background: Background, // Color seen by rays that escape the scene
     image_height: u16,
     center: Point,
     pixel00_location: Point,
@@ -33,19 +54,33 @@ pub struct Camera {
     defocus_disk_v: Direction,
 }
 
+impl Tile {
+    fn width(self) -> usize {
+        usize::from(self.x1 - self.x0)
+    }
+    fn height(self) -> usize {
+        usize::from(self.y1 - self.y0)
+    }
+}
+
 impl Default for Camera {
     fn default() -> Self {
         Self {
             aspect_ratio: 1.0,
             image_width: 100,
             samples_per_pixel: 10,
+            integrator: Integrator::default(),
             max_depth: 10,
+            roulette_depth: 3,
             vfov: 90.0, // Vertical view angle
             look_from: Point::new(0.0, 0.0, -1.0),
             look_at: Point::new(0.0, 0.0, 0.0),
             vup: Direction::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            threads: 0,
+            seed: 0,
+            background: Background::default(),
             image_height: 100,
             center: Point::new(0.0, 0.0, -1.0),
             pixel00_location: Point::default(),
@@ -61,37 +96,96 @@ impl Default for Camera {
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &HittableObject) {
+    pub fn render(&mut self, world: &HittableObject) -> Image {
         self.initialize();
         // Render
-        println!("P3");
-        println!("{} {}", self.image_width, self.image_height);
-        println!("255");
+        let image = Mutex::new(Image::new(
+            usize::from(self.image_width),
+            usize::from(self.image_height),
+        ));
+        let tiles = self.tiles();
+        let lights = LightList::new(world);
+        let world = World {
+            objects: world,
+            lights: &lights,
+            background: &self.background,
+        };
+        let next_tile = AtomicUsize::new(0);
+
+        let pb = ProgressBar::new(tiles.len() as u64);
+
+        let camera = &*self;
+        thread::scope(|s| {
+            for _ in 0..self.thread_count().min(tiles.len()) {
+                s.spawn(|| {
+                    // Workers keep claiming the next unrendered tile until none are left
+                    while let Some(&tile) = tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+                        let pixels = camera.render_tile(tile, &world);
+                        let mut image = image.lock().unwrap();
+                        for (j, row) in (tile.y0..tile.y1).zip(pixels.chunks(tile.width())) {
+                            for (i, &pixel_color) in (tile.x0..tile.x1).zip(row) {
+                                image.set(usize::from(i), usize::from(j), pixel_color);
+                            }
+                        }
+                        drop(image);
+                        pb.inc(1);
+                    }
+                });
+            }
+        });
+        pb.finish();
+        image.into_inner().unwrap()
+    }
+
+    fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            self.threads
+        } else {
+            thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        }
+    }
 
-        let pb = ProgressBar::new(u64::from(self.image_height));
+    fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(usize::from(TILE_SIZE)) {
+            for x0 in (0..self.image_width).step_by(usize::from(TILE_SIZE)) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: x0.saturating_add(TILE_SIZE).min(self.image_width),
+                    y1: y0.saturating_add(TILE_SIZE).min(self.image_height),
+                });
+            }
+        }
+        tiles
+    }
 
-        // pb.println("Starting".to_string());
-        for j in 0..self.image_height {
-            pb.inc(1);
-            for i in 0..self.image_width {
+    fn render_tile(&self, tile: Tile, world: &World) -> Vec<Color> {
+        let length = PathLength {
+            max_depth: self.max_depth,
+            roulette_depth: self.roulette_depth,
+        };
+        let pixel_samples_scale = 1.0 / f32::from(self.samples_per_pixel);
+        let mut pixels = Vec::with_capacity(tile.width() * tile.height());
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
                 let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..(self.samples_per_pixel) {
-                    let r: Ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color(&r, self.max_depth, world);
+                for sample in 0..(self.samples_per_pixel) {
+                    let mut rng = Sampler::for_sample(self.seed, i, j, sample);
+                    let r: Ray = self.get_ray(i, j, &mut rng);
+                    pixel_color += self.integrator.radiance(r, world, length, &mut rng);
                 }
-                color::write_color(pixel_color, self.samples_per_pixel);
+                pixels.push(pixel_color * pixel_samples_scale);
             }
         }
-        // pb.println("format!("Done")");
+        pixels
     }
+
     fn initialize(&mut self) {
         // Calculate image_height, ensure it is at least 1
-        self.image_height = (f32::from(self.image_width) / self.aspect_ratio) as u16;
-        self.image_height = if self.image_height < 1 {
-            1
-        } else {
-            self.image_height
-        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let image_height = (f32::from(self.image_width) / self.aspect_ratio) as u16;
+        self.image_height = image_height.max(1);
 
         self.center = self.look_from;
 
@@ -111,7 +205,7 @@ impl Camera {
 
         // The vectors along the edges of the viewport
         let viewport_u = self.u * viewport_width;
-        let viewport_v = self.v * (viewport_height * (-1.0));
+        let viewport_v = self.v * -viewport_height;
 
         // Horizontal and vertical delta vectors from pixel to pixel.
         self.pixel_delta_u = viewport_u / f32::from(self.image_width);
@@ -129,53 +223,90 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    fn get_ray(&self, i: u16, j: u16) -> Ray {
+    fn get_ray(&self, i: u16, j: u16, rng: &mut Sampler) -> Ray {
         // Get a random ray originating form the camera defocus disk
         let pixel_center = self.pixel00_location
             + (self.pixel_delta_u * f32::from(i))
             + (self.pixel_delta_v * f32::from(j));
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        let pixel_sample = pixel_center + self.pixel_sample_square(rng);
 
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(rng)
         };
         let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random::number(0.0, 1.0);
+        let ray_time = random::number(rng, 0.0, 1.0);
 
         Ray::new(ray_origin, ray_direction, ray_time)
     }
 
-    fn defocus_disk_sample(&self) -> Point {
+    fn defocus_disk_sample(&self, rng: &mut Sampler) -> Point {
         // Returns a random point on the defocus disk sample
-        let p = random::vec3_in_unit_disk();
+        let (p, _) = random::concentric_disk(rng);
         self.center + (self.defocus_disk_u * p.x) + (self.defocus_disk_v * p.y)
     }
 
-    fn pixel_sample_square(&self) -> Point {
-        let px = -0.5 + random::number(0.0, 1.0);
-        let py = -0.5 + random::number(0.0, 1.0);
+    fn pixel_sample_square(&self, rng: &mut Sampler) -> Point {
+        let px = -0.5 + random::number(rng, 0.0, 1.0);
+        let py = -0.5 + random::number(rng, 0.0, 1.0);
         self.pixel_delta_u * px + self.pixel_delta_v * py
     }
+}
 
-    fn ray_color(ray: &Ray, depth: u16, world: &HittableObject) -> Color {
-        let mut rec = HitRecord::default();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
 
-        if depth == 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-        if world.hit(ray, Interval::new(0.001, f32::INFINITY), &mut rec) {
-            let mut scattered = Ray::default();
-            let mut attenuation = Color::default();
-            if rec.mat.scatter(ray, &rec, &mut attenuation, &mut scattered) {
-                return Self::ray_color(&scattered, depth - 1, world) * attenuation;
+    /// White furnace: lossless diffuse walls under a uniform white sky reflect
+    /// exactly as much light as they receive, so every pixel should come out
+    /// white however many bounces the paths need. The walls form a deep tube
+    /// whose only opening is behind the camera, so most paths bounce many times.
+    fn furnace(max_depth: u16, roulette_depth: u16) -> f32 {
+        let white = Material::lambertian(Color::ONE);
+        let mut walls = HittableObject::cuboid(
+            Point::new(-0.5, -0.5, -8.0),
+            Point::new(0.5, 0.5, 0.0),
+            &white,
+        );
+        // The face at z = 0 is the first one, leave it out to open the tube
+        walls.objects.remove(0);
+        let world = HittableObject::bvh(walls.objects);
+
+        let mut camera = Camera {
+            image_width: 8,
+            samples_per_pixel: 512,
+            max_depth,
+            roulette_depth,
+            look_from: Point::new(0.0, 0.0, -0.5),
+            look_at: Point::new(0.0, 0.0, -8.0),
+            vfov: 60.0,
+            background: Background::solid(Color::ONE),
+            ..Camera::default()
+        };
+        let image = camera.render(&world);
+
+        let mut sum = 0.0;
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                sum += image.get(x, y).element_sum() / 3.0;
             }
-            return Color::new(0.0, 0.0, 0.0);
         }
+        #[allow(clippy::cast_precision_loss)]
+        let mean = sum / (image.width() * image.height()) as f32;
+        mean
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_furnace_white() {
+        let mean = furnace(u16::MAX, 2);
+        assert!((mean - 1.0).abs() < 0.02, "furnace came out {mean}");
+    }
 
-        let unit_direction = ray.direction().normalize();
-        let a = 0.5 * (unit_direction.y + 1.0);
-        Color::new(1.0, 1.0, 1.0) * (1.0 - a) + Color::new(0.5, 0.7, 1.0) * a
+    #[test]
+    fn cutting_paths_short_darkens_the_furnace() {
+        let mean = furnace(4, u16::MAX);
+        assert!(mean < 0.9, "furnace came out {mean}");
     }
 }
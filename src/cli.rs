@@ -0,0 +1,198 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::camera::Camera;
+use crate::integrator::{self, Integrator};
+use crate::scene;
+
+pub const USAGE: &str = "\
+Usage: raytracin [OPTIONS]
+
+Options:
+  --scene <name>      Scene to render (default: two-spheres)
+  --scene-file <file> Load the scene from a scene description file instead
+  --width <pixels>    Image width in pixels
+  --aspect <ratio>    Aspect ratio as a number or as W:H, e.g. 1.5 or 16:9
+  --spp <count>       Samples per pixel
+  --max-depth <n>     Maximum number of ray bounces
+  --roulette-depth <n>
+                      Bounces before Russian roulette may end a path
+  --integrator <name> Integrator to render with, listed below (default: path)
+  --seed <n>          Seed for the scene layout and every sample (default: 0)
+  --threads <n>       Worker threads, 0 uses every core (default: 0)
+  --output <file>     Output image, .ppm writes binary PPM, anything else PNG
+                      (default: image.png)
+  --list-scenes       Print the available scenes and exit
+  --bench-bvh         Time the SAH and median split BVH builders and exit
+  -h, --help          Print this help and exit
+";
+
+/// Settings collected from the command line. Camera settings left as `None`
+/// keep whatever value the scene chose.
+#[derive(Debug)]
+pub struct Options {
+    pub scene: String,
+    pub scene_file: Option<PathBuf>,
+    pub width: Option<u16>,
+    pub aspect: Option<f32>,
+    pub spp: Option<u16>,
+    pub max_depth: Option<u16>,
+    pub roulette_depth: Option<u16>,
+    pub integrator: Option<Integrator>,
+    pub seed: u64,
+    pub threads: Option<usize>,
+    pub output: PathBuf,
+    pub list_scenes: bool,
+    pub bench_bvh: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scene: String::from("two-spheres"),
+            scene_file: None,
+            width: None,
+            aspect: None,
+            spp: None,
+            max_depth: None,
+            roulette_depth: None,
+            integrator: None,
+            seed: 0,
+            threads: None,
+            output: PathBuf::from("image.png"),
+            list_scenes: false,
+            bench_bvh: false,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub const fn apply(&self, camera: &mut Camera) {
+        if let Some(width) = self.width {
+            camera.image_width = width;
+        }
+        if let Some(aspect) = self.aspect {
+            camera.aspect_ratio = aspect;
+        }
+        if let Some(spp) = self.spp {
+            camera.samples_per_pixel = spp;
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera.max_depth = max_depth;
+        }
+        if let Some(roulette_depth) = self.roulette_depth {
+            camera.roulette_depth = roulette_depth;
+        }
+        if let Some(integrator) = self.integrator {
+            camera.integrator = integrator;
+        }
+        if let Some(threads) = self.threads {
+            camera.threads = threads;
+        }
+        camera.seed = self.seed;
+    }
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for {flag}"))
+        };
+
+        match flag.as_str() {
+            "--scene" => {
+                let name = value()?;
+                if scene::find(&name).is_none() {
+                    return Err(format!(
+                        "unknown scene '{name}', available scenes: {}",
+                        scene::names().join(", ")
+                    ));
+                }
+                options.scene = name;
+            }
+            "--scene-file" => options.scene_file = Some(PathBuf::from(value()?)),
+            "--width" => options.width = Some(positive(&flag, &value()?)?),
+            "--aspect" => options.aspect = Some(aspect_ratio(&value()?)?),
+            "--spp" => options.spp = Some(positive(&flag, &value()?)?),
+            "--max-depth" => options.max_depth = Some(positive(&flag, &value()?)?),
+            "--roulette-depth" => options.roulette_depth = Some(number(&flag, &value()?)?),
+            "--integrator" => {
+                let name = value()?;
+                let Some(integrator) = integrator::find(&name) else {
+                    return Err(format!(
+                        "unknown integrator '{name}', available integrators: {}",
+                        integrator::names().join(", ")
+                    ));
+                };
+                options.integrator = Some(integrator);
+            }
+            "--seed" => options.seed = number(&flag, &value()?)?,
+            "--threads" => options.threads = Some(number(&flag, &value()?)?),
+            "--output" => options.output = PathBuf::from(value()?),
+            "--list-scenes" => options.list_scenes = true,
+            "--bench-bvh" => options.bench_bvh = true,
+            "-h" | "--help" => options.help = true,
+            _ => return Err(format!("unknown argument '{flag}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn number<T>(flag: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value '{value}' for {flag}: {e}"))
+}
+
+fn positive<T>(flag: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr + Default + PartialOrd,
+    T::Err: Display,
+{
+    let n = number(flag, value)?;
+    if n > T::default() {
+        Ok(n)
+    } else {
+        Err(format!(
+            "invalid value '{value}' for {flag}: must be at least 1"
+        ))
+    }
+}
+
+fn aspect_ratio(value: &str) -> Result<f32, String> {
+    let invalid =
+        || format!("invalid value '{value}' for --aspect: expected a ratio such as 1.5 or 16:9");
+    let ratio = match value.split_once([':', '/']) {
+        Some((w, h)) => {
+            let w: f32 = w.trim().parse().map_err(|_| invalid())?;
+            let h: f32 = h.trim().parse().map_err(|_| invalid())?;
+            w / h
+        }
+        None => value.parse().map_err(|_| invalid())?,
+    };
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(invalid())
+    }
+}
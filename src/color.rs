@@ -7,20 +7,26 @@ fn linear_to_gamma(linear_component: f32) -> f32 {
     linear_component.sqrt()
 }
 
-pub fn write_color(pixel_color: Color, samples_per_pixel: u16) {
-    //Divide the color by the number of samples.
-    let scale = 1.0 / f32::from(samples_per_pixel);
+/// Decodes an sRGB encoded component, as stored in 8-bit image files, to linear light.
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
 
-    let r = linear_to_gamma(pixel_color.x * scale);
-    let g = linear_to_gamma(pixel_color.y * scale);
-    let b = linear_to_gamma(pixel_color.z * scale);
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn to_rgb8(pixel_color: Color) -> [u8; 3] {
+    let r = linear_to_gamma(pixel_color.x);
+    let g = linear_to_gamma(pixel_color.y);
+    let b = linear_to_gamma(pixel_color.z);
 
-    // Write th translated [0,255] value  of each color component
+    // Translate to the [0,255] value of each color component
     let intensity = Interval::new(0.000, 0.999);
-    println!(
-        "{} {} {}",
-        (256.0 * intensity.clamp(r)) as i16,
-        (256.0 * intensity.clamp(g)) as i16,
-        (256.0 * intensity.clamp(b)) as i16
-    );
+    [
+        (256.0 * intensity.clamp(r)) as u8,
+        (256.0 * intensity.clamp(g)) as u8,
+        (256.0 * intensity.clamp(b)) as u8,
+    ]
 }
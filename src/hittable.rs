@@ -1,17 +1,27 @@
 mod hit_record;
 mod hittable_list;
 mod hittable_object;
+mod light_list;
 
 pub use hit_record::HitRecord;
 pub use hittable_list::HittableList;
-pub use hittable_object::bvh_node::BvhNode;
-pub use hittable_object::HittableObject;
+pub use hittable_object::{HittableObject, MeshData};
+pub use light_list::{LightList, LightSample};
 
 use crate::aabb::Aabb;
 use crate::interval::Interval;
+use crate::random::Sampler;
 use crate::ray::Ray;
 
 pub trait Hittable {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut hit_record::HitRecord) -> bool;
+    /// Finds the nearest hit along `r` within `ray_t`. Media draw where rays
+    /// scatter in them from `rng`.
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: Interval,
+        rec: &mut hit_record::HitRecord,
+        rng: &mut Sampler,
+    ) -> bool;
     fn bounding_box(&self) -> &Aabb;
 }
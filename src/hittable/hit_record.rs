@@ -27,4 +27,14 @@ impl HitRecord {
             (*outward_normal) * (-1.0)
         }
     }
+
+    /// Cosine between the normal and light arriving from `direction`. Points
+    /// inside a medium have no surface to spread the light over, so it is 1 there.
+    pub fn foreshortening(&self, direction: Direction) -> f32 {
+        if self.mat.is_volumetric() {
+            1.0
+        } else {
+            self.normal.dot(direction).abs()
+        }
+    }
 }
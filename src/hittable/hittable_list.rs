@@ -1,6 +1,7 @@
 use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable, HittableObject};
 use crate::interval::Interval;
+use crate::random::Sampler;
 
 #[derive(Default)]
 pub struct HittableList {
@@ -20,13 +21,21 @@ impl HittableList {
         self.bbox = Aabb::new_with_boxes(self.bounding_box(), object.bounding_box());
         self.objects.push(object);
     }
-    pub fn objects(&mut self) -> &mut [HittableObject] {
-        &mut self.objects
+    pub fn append(&mut self, other: Self) {
+        for object in other.objects {
+            self.add(object);
+        }
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &crate::ray::Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        ray_t: Interval,
+        rec: &mut HitRecord,
+        rng: &mut Sampler,
+    ) -> bool {
         let mut temp_record = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max();
@@ -36,6 +45,7 @@ impl Hittable for HittableList {
                 r,
                 Interval::new(ray_t.min(), closest_so_far),
                 &mut temp_record,
+                rng,
             ) {
                 hit_anything = true;
                 closest_so_far = temp_record.t;
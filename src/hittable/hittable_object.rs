@@ -1,16 +1,38 @@
-pub mod bvh_node;
+mod bvh;
+mod constant_medium;
+mod heterogeneous_medium;
+mod instance;
+mod mesh;
+mod quad;
 mod sphere;
+mod triangle;
+
+pub use triangle::MeshData;
+
+use std::sync::Arc;
+
+use glam::Affine3A;
 
 use crate::aabb::Aabb;
-use crate::hittable::{HitRecord, Hittable};
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable, HittableList, LightSample};
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::random::Sampler;
 use crate::ray::{Direction, Point, Ray};
+use crate::texture::Texture;
+use crate::voxel_grid::VoxelGrid;
 
 #[derive(Clone)]
 pub enum HittableObject {
     Sphere(sphere::Sphere),
-    BvhNode(bvh_node::BvhNode),
+    Bvh(bvh::Bvh),
+    Quad(quad::Quad),
+    Triangle(triangle::Triangle),
+    Mesh(mesh::Mesh),
+    Instance(instance::Instance),
+    ConstantMedium(constant_medium::ConstantMedium),
+    HeterogeneousMedium(heterogeneous_medium::HeterogeneousMedium),
 }
 
 impl HittableObject {
@@ -19,8 +41,12 @@ impl HittableObject {
         let bbox = Aabb::new(center - rvec, center + rvec);
         Self::Sphere(sphere::Sphere::new(center, center, radius, mat, bbox))
     }
-    pub fn bvh_node(src_objects: &mut [Self]) -> Self {
-        Self::BvhNode(bvh_node::BvhNode::new_from_vector(src_objects))
+    pub fn bvh(objects: Vec<Self>) -> Self {
+        Self::Bvh(bvh::Bvh::new(objects))
+    }
+    /// A BVH from the older random axis median split builder, for comparisons.
+    pub fn random_median_bvh(objects: Vec<Self>, rng: &mut Sampler) -> Self {
+        Self::Bvh(bvh::Bvh::new_random_median(objects, rng))
     }
     pub fn moving_sphere(center1: Point, center2: Point, radius: f32, mat: Material) -> Self {
         let rvec = Direction::new(radius, radius, radius);
@@ -29,20 +55,203 @@ impl HittableObject {
         let bbox = Aabb::new_with_boxes(&box1, &box2);
         Self::Sphere(sphere::Sphere::new(center1, center2, radius, mat, bbox))
     }
+    pub fn quad(q: Point, u: Direction, v: Direction, mat: Material) -> Self {
+        // Compute the bounding box of all four vertices, padded so a flat quad
+        // still has some thickness along its own axis.
+        let bbox_diagonal1 = Aabb::new(q, q + u + v);
+        let bbox_diagonal2 = Aabb::new(q + u, q + v);
+        let bbox = Aabb::new_with_boxes(&bbox_diagonal1, &bbox_diagonal2).pad_to_minimums();
+        Self::Quad(quad::Quad::new(q, u, v, mat, bbox))
+    }
+    pub fn triangle(a: Point, b: Point, c: Point, mat: Material) -> Self {
+        let data = MeshData {
+            positions: vec![a, b, c],
+            indices: vec![[0, 1, 2]],
+            materials: vec![mat],
+            ..MeshData::default()
+        };
+        Self::Triangle(triangle::Triangle::new(Arc::new(data), 0))
+    }
+    pub fn mesh(data: MeshData) -> Self {
+        Self::Mesh(mesh::Mesh::new(data))
+    }
+    /// Every triangle of a mesh as a separate object, without a BVH around them.
+    pub fn mesh_triangles(data: MeshData) -> Vec<Self> {
+        let data = Arc::new(data);
+        (0..data.triangle_count())
+            .map(|i| Self::Triangle(triangle::Triangle::new(Arc::clone(&data), i)))
+            .collect()
+    }
+    /// Places a shared object in the world, e.g. with
+    /// `Affine3A::from_scale_rotation_translation(scale, rotation, offset)`.
+    pub fn instance(object: Arc<Self>, transform: Affine3A) -> Self {
+        Self::Instance(instance::Instance::new(object, transform))
+    }
+    /// Fills a closed, convex `boundary` with a medium of uniform `density` per
+    /// unit length that scatters with `phase_function`, e.g. [`Material::isotropic`].
+    pub fn constant_medium(boundary: Self, density: f32, phase_function: Material) -> Self {
+        Self::ConstantMedium(constant_medium::ConstantMedium::new(
+            boundary,
+            density,
+            phase_function,
+        ))
+    }
+    /// A medium filling the grid's box with its densities times `scale`.
+    /// `emission` is the glow where the density peaks, black for plain smoke.
+    pub fn grid_medium(
+        grid: Arc<VoxelGrid>,
+        scale: f32,
+        phase_function: Material,
+        emission: Color,
+    ) -> Self {
+        Self::HeterogeneousMedium(heterogeneous_medium::HeterogeneousMedium::grid(
+            grid,
+            scale,
+            phase_function,
+            emission,
+        ))
+    }
+    /// A medium filling a closed, convex `boundary` with a procedural density:
+    /// the red channel of `texture`, e.g. a noise texture, clamped to [0, 1]
+    /// and times `scale`.
+    pub fn texture_medium(
+        boundary: Self,
+        texture: Texture,
+        scale: f32,
+        phase_function: Material,
+        emission: Color,
+    ) -> Self {
+        Self::HeterogeneousMedium(heterogeneous_medium::HeterogeneousMedium::texture(
+            boundary,
+            texture,
+            scale,
+            phase_function,
+            emission,
+        ))
+    }
+    /// Returns the box (six sides) that contains the two opposite vertices a & b.
+    pub fn cuboid(a: Point, b: Point, mat: &Material) -> HittableList {
+        let mut sides = HittableList::default();
+
+        // Construct the two opposite vertices with the minimum and maximum coordinates.
+        let min = a.min(b);
+        let max = a.max(b);
+
+        let dx = Direction::new(max.x - min.x, 0.0, 0.0);
+        let dy = Direction::new(0.0, max.y - min.y, 0.0);
+        let dz = Direction::new(0.0, 0.0, max.z - min.z);
+
+        let front = Point::new(min.x, min.y, max.z);
+        let right = Point::new(max.x, min.y, max.z);
+        let back = Point::new(max.x, min.y, min.z);
+        let left = Point::new(min.x, min.y, min.z);
+        let top = Point::new(min.x, max.y, max.z);
+
+        sides.add(Self::quad(front, dx, dy, mat.clone()));
+        sides.add(Self::quad(right, -dz, dy, mat.clone()));
+        sides.add(Self::quad(back, -dx, dy, mat.clone()));
+        sides.add(Self::quad(left, dz, dy, mat.clone()));
+        sides.add(Self::quad(top, dx, -dz, mat.clone()));
+        sides.add(Self::quad(min, dx, dz, mat.clone()));
+
+        sides
+    }
+    /// Adds every emissive sphere, quad and triangle inside this object to
+    /// `lights`, wrapped in the transforms of the instances around it.
+    pub fn collect_lights(&self, lights: &mut Vec<Self>) {
+        match self {
+            Self::Sphere(sphere) if sphere.material().is_light() => lights.push(self.clone()),
+            Self::Quad(quad) if quad.material().is_light() => lights.push(self.clone()),
+            Self::Triangle(triangle) if triangle.material().is_light() => {
+                lights.push(self.clone());
+            }
+            Self::Bvh(bvh) => bvh.objects().iter().for_each(|o| o.collect_lights(lights)),
+            Self::Mesh(mesh) => mesh.collect_lights(lights),
+            Self::Instance(instance) => {
+                let mut inner = Vec::new();
+                instance.object().collect_lights(&mut inner);
+                lights.extend(inner.into_iter().map(|light| instance.with_object(light)));
+            }
+            Self::Sphere(_)
+            | Self::Quad(_)
+            | Self::Triangle(_)
+            | Self::ConstantMedium(_)
+            | Self::HeterogeneousMedium(_) => {}
+        }
+    }
+    /// Samples a point on a light collected by [`Self::collect_lights`], as seen from `origin`.
+    pub fn sample_light(&self, origin: Point, time: f32, rng: &mut Sampler) -> Option<LightSample> {
+        match self {
+            Self::Sphere(sphere) => sphere.sample(origin, time, rng),
+            Self::Quad(quad) => quad.sample(origin, rng),
+            Self::Triangle(triangle) => triangle.sample(origin, rng),
+            Self::Instance(instance) => instance.sample(origin, time, rng),
+            Self::Bvh(_)
+            | Self::Mesh(_)
+            | Self::ConstantMedium(_)
+            | Self::HeterogeneousMedium(_) => None,
+        }
+    }
+    /// The sample [`Self::sample_light`] takes to pick `direction`, used for its density.
+    pub fn light_sample_toward(
+        &self,
+        origin: Point,
+        direction: Direction,
+        time: f32,
+    ) -> Option<LightSample> {
+        match self {
+            Self::Sphere(sphere) => sphere.sample_toward(origin, direction, time),
+            Self::Quad(quad) => quad.sample_toward(origin, direction),
+            Self::Triangle(triangle) => triangle.sample_toward(origin, direction),
+            Self::Instance(instance) => instance.sample_toward(origin, direction, time),
+            Self::Bvh(_)
+            | Self::Mesh(_)
+            | Self::ConstantMedium(_)
+            | Self::HeterogeneousMedium(_) => None,
+        }
+    }
+    /// Fraction of light that makes it along `r` within `ray_t`: zero when a
+    /// surface is in the way, otherwise what the media on the way let through.
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Sampler) -> f32 {
+        match self {
+            Self::Bvh(bvh) => bvh.transmittance(r, ray_t, rng),
+            Self::Instance(instance) => instance.transmittance(r, ray_t, rng),
+            Self::ConstantMedium(medium) => medium.transmittance(r, ray_t, rng),
+            Self::HeterogeneousMedium(medium) => medium.transmittance(r, ray_t, rng),
+            Self::Sphere(_) | Self::Quad(_) | Self::Triangle(_) | Self::Mesh(_) => {
+                if self.hit(r, ray_t, &mut HitRecord::default(), rng) {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
 }
 
 impl Hittable for HittableObject {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
         match self {
-            Self::Sphere(sphere) => sphere.hit(r, ray_t, rec),
-            Self::BvhNode(bvh_node) => bvh_node.hit(r, ray_t, rec),
-            // HittableObject::Cube(cub) => cub.hit(r, ray_t, rec)
+            Self::Sphere(sphere) => sphere.hit(r, ray_t, rec, rng),
+            Self::Bvh(bvh) => bvh.hit(r, ray_t, rec, rng),
+            Self::Quad(quad) => quad.hit(r, ray_t, rec, rng),
+            Self::Triangle(triangle) => triangle.hit(r, ray_t, rec, rng),
+            Self::Mesh(mesh) => mesh.hit(r, ray_t, rec, rng),
+            Self::Instance(instance) => instance.hit(r, ray_t, rec, rng),
+            Self::ConstantMedium(medium) => medium.hit(r, ray_t, rec, rng),
+            Self::HeterogeneousMedium(medium) => medium.hit(r, ray_t, rec, rng),
         }
     }
     fn bounding_box(&self) -> &Aabb {
         match self {
-            Self::Sphere(sphere) => return sphere.bounding_box(),
-            Self::BvhNode(bvh_node) => return bvh_node.bounding_box(),
+            Self::Sphere(sphere) => sphere.bounding_box(),
+            Self::Bvh(bvh) => bvh.bounding_box(),
+            Self::Quad(quad) => quad.bounding_box(),
+            Self::Triangle(triangle) => triangle.bounding_box(),
+            Self::Mesh(mesh) => mesh.bounding_box(),
+            Self::Instance(instance) => instance.bounding_box(),
+            Self::ConstantMedium(medium) => medium.bounding_box(),
+            Self::HeterogeneousMedium(medium) => medium.bounding_box(),
         }
     }
 }
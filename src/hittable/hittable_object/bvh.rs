@@ -0,0 +1,319 @@
+use super::HittableObject;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Ray};
+
+// Number of centroid buckets tried per axis when looking for the cheapest split
+const BIN_COUNT: usize = 12;
+// Larger leaves are always split, smaller ones only when the split is cheaper
+const MAX_LEAF_SIZE: usize = 4;
+// Cost of visiting a node, relative to intersecting one object
+const TRAVERSAL_COST: f32 = 0.125;
+// Room on the traversal stack. Below SAH_MAX_DEPTH nodes are halved by count,
+// which bounds the depth of the tree by SAH_MAX_DEPTH + 32.
+const STACK_SIZE: usize = 64;
+const SAH_MAX_DEPTH: usize = STACK_SIZE - 32;
+
+/// One node of the flattened tree. Nodes are stored depth first, so the first
+/// child of an interior node always directly follows it.
+#[derive(Debug, Clone)]
+struct Node {
+    bbox: Aabb,
+    offset: u32, // Leaf: index of the first object. Interior: index of the second child.
+    count: u16,  // Number of objects in a leaf, 0 for interior nodes
+    axis: u8,    // Interior: the axis the children were split along
+}
+
+/// A bounding volume hierarchy compiled into one array of nodes over one
+/// array of objects, where every leaf covers a contiguous range of objects.
+#[derive(Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    objects: Vec<HittableObject>,
+}
+
+/// How the builder divides the objects of a node between its two children.
+enum Strategy<'a> {
+    SurfaceArea,
+    RandomMedian(&'a mut Sampler),
+}
+
+impl Bvh {
+    /// Builds the tree top down with the binned surface area heuristic: every
+    /// node is split where the expected cost of a ray hitting both halves is
+    /// lowest, or becomes a leaf when that is cheaper than any split.
+    pub fn new(objects: Vec<HittableObject>) -> Self {
+        Self::build(objects, &mut Strategy::SurfaceArea)
+    }
+
+    /// The original builder: a random axis and a split at the median object,
+    /// one object per leaf. Kept to compare against [`Bvh::new`].
+    pub fn new_random_median(objects: Vec<HittableObject>, rng: &mut Sampler) -> Self {
+        Self::build(objects, &mut Strategy::RandomMedian(rng))
+    }
+
+    pub fn objects(&self) -> &[HittableObject] {
+        &self.objects
+    }
+
+    fn build(mut objects: Vec<HittableObject>, strategy: &mut Strategy) -> Self {
+        let mut nodes = Vec::with_capacity(2 * objects.len());
+        build_node(&mut nodes, &mut objects, 0, 0, strategy);
+        Self { nodes, objects }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn build_node(
+    nodes: &mut Vec<Node>,
+    objects: &mut [HittableObject],
+    first: usize,
+    depth: usize,
+    strategy: &mut Strategy,
+) {
+    let bbox = objects.iter().fold(Aabb::default(), |bbox, object| {
+        Aabb::new_with_boxes(&bbox, object.bounding_box())
+    });
+    let index = nodes.len();
+    nodes.push(Node {
+        bbox: bbox.clone(),
+        offset: first as u32,
+        count: 0,
+        axis: 0,
+    });
+
+    let Some((axis, mid)) = split(objects, &bbox, depth, strategy) else {
+        nodes[index].count =
+            u16::try_from(objects.len()).expect("leaves hold at most MAX_LEAF_SIZE objects");
+        return;
+    };
+    let (left, right) = objects.split_at_mut(mid);
+    build_node(nodes, left, first, depth + 1, strategy);
+    nodes[index].offset = nodes.len() as u32;
+    nodes[index].axis = axis as u8;
+    build_node(nodes, right, first + mid, depth + 1, strategy);
+}
+
+/// Reorders `objects` into the two children and returns the split axis and
+/// the number of objects going left, or `None` when the node stays a leaf.
+fn split(
+    objects: &mut [HittableObject],
+    bbox: &Aabb,
+    depth: usize,
+    strategy: &mut Strategy,
+) -> Option<(usize, usize)> {
+    let count = objects.len();
+    if count <= 1 {
+        return None;
+    }
+    let must_split = count > MAX_LEAF_SIZE;
+
+    let centroids = objects.iter().fold(Aabb::default(), |bounds, object| {
+        let c = object.bounding_box().centroid();
+        Aabb::new_with_boxes(&bounds, &Aabb::new(c, c))
+    });
+    let widest_axis = (0..3)
+        .max_by(|&a, &b| {
+            centroids
+                .axis(a)
+                .size()
+                .total_cmp(&centroids.axis(b).size())
+        })
+        .unwrap_or(0);
+    let median = |objects: &mut [HittableObject], axis: usize| {
+        objects.select_nth_unstable_by(count / 2, |a, b| {
+            let a = a.bounding_box().centroid()[axis];
+            let b = b.bounding_box().centroid()[axis];
+            a.total_cmp(&b)
+        });
+        Some((axis, count / 2))
+    };
+
+    match strategy {
+        Strategy::RandomMedian(rng) => median(objects, random::number(rng, 0, 3)),
+        // Past this depth only balanced splits, so the traversal stack cannot overflow
+        Strategy::SurfaceArea if depth >= SAH_MAX_DEPTH => median(objects, widest_axis),
+        Strategy::SurfaceArea => match cheapest_split(objects, bbox, &centroids) {
+            Some((cost, _)) if !must_split && cost >= leaf_cost(count) => None,
+            Some((_, split)) => {
+                let in_left = |object: &HittableObject| split.bin(object, &centroids) < split.bin;
+                Some((split.axis, partition(objects, in_left)))
+            }
+            // Every centroid is in the same place, no split can separate them
+            None if !must_split => None,
+            None => median(objects, widest_axis),
+        },
+    }
+}
+
+/// A candidate split: objects whose centroid falls in a bin below `bin` go left.
+#[derive(Clone, Copy)]
+struct Split {
+    axis: usize,
+    bin: usize,
+}
+
+impl Split {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn bin(self, object: &HittableObject, centroids: &Aabb) -> usize {
+        let extent = centroids.axis(self.axis);
+        let offset = (object.bounding_box().centroid()[self.axis] - extent.min) / extent.size();
+        ((offset * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+const fn leaf_cost(count: usize) -> f32 {
+    count as f32
+}
+
+/// Tries every bin boundary on every axis and returns the cheapest split with its cost.
+#[allow(clippy::cast_precision_loss)]
+fn cheapest_split(
+    objects: &[HittableObject],
+    bbox: &Aabb,
+    centroids: &Aabb,
+) -> Option<(f32, Split)> {
+    let mut best: Option<(f32, Split)> = None;
+    for axis in 0..3 {
+        if centroids.axis(axis).size() <= 0.0 {
+            continue;
+        }
+        let mut bins: [(Aabb, usize); BIN_COUNT] = Default::default();
+        for object in objects {
+            let (bounds, count) = &mut bins[Split { axis, bin: 0 }.bin(object, centroids)];
+            *bounds = Aabb::new_with_boxes(bounds, object.bounding_box());
+            *count += 1;
+        }
+
+        // Sweep from the left to collect what lies below each boundary, then from the right
+        let weighted_area = |bounds: &Aabb, count: usize| {
+            if count == 0 {
+                0.0
+            } else {
+                bounds.surface_area() * count as f32
+            }
+        };
+        let mut below = [0.0; BIN_COUNT];
+        let (mut bounds, mut count) = (Aabb::default(), 0);
+        for bin in 1..BIN_COUNT {
+            bounds = Aabb::new_with_boxes(&bounds, &bins[bin - 1].0);
+            count += bins[bin - 1].1;
+            below[bin] = weighted_area(&bounds, count);
+        }
+        let (mut bounds, mut count) = (Aabb::default(), 0);
+        for bin in (1..BIN_COUNT).rev() {
+            bounds = Aabb::new_with_boxes(&bounds, &bins[bin].0);
+            count += bins[bin].1;
+            let cost = TRAVERSAL_COST
+                + (below[bin] + weighted_area(&bounds, count))
+                    / bbox.surface_area().max(f32::MIN_POSITIVE);
+            if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                best = Some((cost, Split { axis, bin }));
+            }
+        }
+    }
+    best
+}
+
+/// Moves the objects for which `in_left` holds to the front, returns how many there are.
+fn partition(objects: &mut [HittableObject], in_left: impl Fn(&HittableObject) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..objects.len() {
+        if in_left(&objects[i]) {
+            objects.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+impl Bvh {
+    /// Product of the transmittances of every object whose node the ray
+    /// enters, stopping at the first surface in the way.
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Sampler) -> f32 {
+        let mut transmittance = 1.0;
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bbox.hit(r, ray_t) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for object in &self.objects[first..first + usize::from(node.count)] {
+                        transmittance *= object.transmittance(r, ray_t, rng);
+                        if transmittance == 0.0 {
+                            return 0.0;
+                        }
+                    }
+                } else {
+                    stack[stack_len] = node.offset as usize;
+                    stack_len += 1;
+                    index += 1;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
+        }
+        transmittance
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
+        let direction_is_negative = r.direction().cmplt(Direction::ZERO);
+        let mut closest_so_far = ray_t.max;
+        let mut hit_anything = false;
+
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bbox.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for object in &self.objects[first..first + usize::from(node.count)] {
+                        if object.hit(r, Interval::new(ray_t.min, closest_so_far), rec, rng) {
+                            hit_anything = true;
+                            closest_so_far = rec.t;
+                        }
+                    }
+                } else {
+                    // Visit the child nearer along the split axis first, its hits
+                    // shorten the interval the far child is tested against
+                    let (near, far) = if direction_is_negative.test(usize::from(node.axis)) {
+                        (node.offset as usize, index + 1)
+                    } else {
+                        (index + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    index = near;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.nodes[0].bbox
+    }
+}
@@ -0,0 +1,157 @@
+use super::HittableObject;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Ray};
+
+/// A volume of uniform density filling a closed, convex boundary. Rays pass
+/// through it or scatter at a random depth, more likely the denser it is.
+#[derive(Clone)]
+pub struct ConstantMedium {
+    boundary: Box<HittableObject>,
+    neg_inv_density: f32,
+    phase_function: Material,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: HittableObject, density: f32, phase_function: Material) -> Self {
+        Self {
+            boundary: Box::new(boundary),
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+
+    /// Fraction of light that crosses the medium along `r` within `ray_t`.
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Sampler) -> f32 {
+        span(&self.boundary, r, ray_t, rng).map_or(1.0, |(enter_t, leave_t)| {
+            let distance_inside = (leave_t - enter_t) * r.direction().length();
+            (distance_inside / self.neg_inv_density).exp()
+        })
+    }
+}
+
+/// The part of `ray_t` where `r` is inside `boundary`. Looks behind the origin
+/// too, so rays starting inside find where they entered.
+pub fn span(
+    boundary: &HittableObject,
+    r: &Ray,
+    ray_t: Interval,
+    rng: &mut Sampler,
+) -> Option<(f32, f32)> {
+    let mut enter = HitRecord::default();
+    if !boundary.hit(r, Interval::UNIVERSE, &mut enter, rng) {
+        return None;
+    }
+    let mut leave = HitRecord::default();
+    if !boundary.hit(
+        r,
+        Interval::new(enter.t + 0.0001, f32::INFINITY),
+        &mut leave,
+        rng,
+    ) {
+        return None;
+    }
+    let enter_t = enter.t.max(ray_t.min);
+    let leave_t = leave.t.min(ray_t.max);
+    (enter_t < leave_t).then_some((enter_t, leave_t))
+}
+
+/// Fills `rec` for a ray scattering in a medium at `t`, with the phase function `mat`.
+pub fn collide(rec: &mut HitRecord, r: &Ray, t: f32, mat: Material) {
+    rec.t = t;
+    rec.p = r.at(t);
+    rec.normal = Direction::new(1.0, 0.0, 0.0); // Arbitrary, a medium has no surface
+    rec.front_face = true;
+    rec.u = 0.0;
+    rec.v = 0.0;
+    rec.mat = mat;
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
+        let Some((enter_t, leave_t)) = span(&self.boundary, r, ray_t, rng) else {
+            return false;
+        };
+
+        // Free flight distance, exponentially distributed with the density as its rate
+        let ray_length = r.direction().length();
+        let distance_inside = (leave_t - enter_t) * ray_length;
+        let hit_distance = self.neg_inv_density * random::number(rng, 0.0f32, 1.0).ln();
+        if hit_distance > distance_inside {
+            return false;
+        }
+
+        collide(
+            rec,
+            r,
+            enter_t + hit_distance / ray_length,
+            self.phase_function.clone(),
+        );
+        true
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::ray::Point;
+    use crate::texture::Texture;
+
+    /// A unit sphere of density 0.5 around `center`.
+    fn fog_ball(center: Point) -> ConstantMedium {
+        let fog = Material::isotropic(Texture::solid_color(Color::ONE));
+        let sphere = HittableObject::sphere(center, 1.0, fog.clone());
+        ConstantMedium::new(sphere, 0.5, fog)
+    }
+
+    /// Fraction of the times the ray from `origin` along +z crosses all of
+    /// `media` without scattering.
+    #[allow(clippy::cast_precision_loss)]
+    fn transmittance(origin: Point, media: &[ConstantMedium]) -> f32 {
+        let rays = 100_000;
+        let ray = Ray::new(origin, Direction::new(0.0, 0.0, 1.0), 0.0);
+        let mut rng = Sampler::new(47);
+        let passed = (0..rays)
+            .filter(|_| {
+                media.iter().all(|medium| {
+                    let mut rec = HitRecord::default();
+                    let ray_t = Interval::new(0.001, f32::INFINITY);
+                    let hit = medium.hit(&ray, ray_t, &mut rec, &mut rng);
+                    assert!(
+                        !hit || rec.p.distance(medium.bounding_box().centroid()) <= 1.0,
+                        "scattered outside at {}",
+                        rec.p
+                    );
+                    !hit
+                })
+            })
+            .count();
+        passed as f32 / rays as f32
+    }
+
+    #[test]
+    fn rays_through_the_medium_follow_beers_law() {
+        let passed = transmittance(Point::new(0.0, 0.0, -5.0), &[fog_ball(Point::ZERO)]);
+        assert!((passed - (-1.0f32).exp()).abs() < 0.01, "{passed} passed");
+    }
+
+    #[test]
+    fn rays_starting_inside_only_cross_the_rest_of_the_medium() {
+        let passed = transmittance(Point::ZERO, &[fog_ball(Point::ZERO)]);
+        assert!((passed - (-0.5f32).exp()).abs() < 0.01, "{passed} passed");
+    }
+
+    #[test]
+    fn media_on_the_same_ray_scatter_independently() {
+        let media = [fog_ball(Point::ZERO), fog_ball(Point::new(0.0, 0.0, 3.0))];
+        let passed = transmittance(Point::new(0.0, 0.0, -5.0), &media);
+        assert!((passed - (-2.0f32).exp()).abs() < 0.01, "{passed} passed");
+    }
+}
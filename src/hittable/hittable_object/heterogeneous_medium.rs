@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use super::constant_medium::{collide, span};
+use super::HittableObject;
+use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::random::{self, Sampler};
+use crate::ray::{Point, Ray};
+use crate::texture::Texture;
+use crate::voxel_grid::VoxelGrid;
+
+/// Where the density of a heterogeneous medium comes from.
+#[derive(Debug, Clone)]
+enum Density {
+    Grid(Arc<VoxelGrid>),
+    Texture(Texture), // The red channel, clamped to [0, 1]
+}
+
+/// A volume whose density varies from point to point inside a closed, convex
+/// boundary. Distances are sampled against a majorant, a density at least as
+/// high as any in the volume: delta tracking finds where rays scatter, ratio
+/// tracking how much light gets through.
+#[derive(Clone)]
+pub struct HeterogeneousMedium {
+    boundary: Box<HittableObject>,
+    density: Density,
+    scale: f32,    // Multiplier for the grid or texture values
+    majorant: f32, // Upper bound of the scaled density
+    phase_function: Material,
+    emission: Color, // Glow where the density reaches the majorant, for fire
+}
+
+impl HeterogeneousMedium {
+    /// The grid's densities times `scale`, filling the grid's box.
+    pub fn grid(
+        grid: Arc<VoxelGrid>,
+        scale: f32,
+        phase_function: Material,
+        emission: Color,
+    ) -> Self {
+        let sides = HittableObject::cuboid(grid.min(), grid.max(), &phase_function);
+        Self {
+            boundary: Box::new(HittableObject::bvh(sides.objects)),
+            majorant: scale * grid.max_density(),
+            density: Density::Grid(grid),
+            scale,
+            phase_function,
+            emission,
+        }
+    }
+
+    /// The texture's red channel, clamped to [0, 1] and times `scale`, filling `boundary`.
+    pub fn texture(
+        boundary: HittableObject,
+        texture: Texture,
+        scale: f32,
+        phase_function: Material,
+        emission: Color,
+    ) -> Self {
+        Self {
+            boundary: Box::new(boundary),
+            density: Density::Texture(texture),
+            scale,
+            majorant: scale,
+            phase_function,
+            emission,
+        }
+    }
+
+    fn density(&self, p: Point) -> f32 {
+        let density = match &self.density {
+            Density::Grid(grid) => grid.density(p),
+            Density::Texture(texture) => texture.value(0.0, 0.0, &p).x.clamp(0.0, 1.0),
+        };
+        density * self.scale
+    }
+
+    /// Samples the next tentative collision after `t`: distances exponentially
+    /// distributed with the majorant as their rate, in units of the ray parameter.
+    fn step(&self, t: f32, ray_length: f32, rng: &mut Sampler) -> f32 {
+        let u: f32 = random::number(rng, 0.0, 1.0);
+        t - (1.0 - u).ln() / (self.majorant * ray_length)
+    }
+
+    /// Ratio tracking: the fraction of light that crosses the medium along `r`
+    /// within `ray_t`, estimated by weighting every tentative collision by the
+    /// chance that it is not a real one.
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Sampler) -> f32 {
+        let Some((enter_t, leave_t)) = span(&self.boundary, r, ray_t, rng) else {
+            return 1.0;
+        };
+        if self.majorant <= 0.0 {
+            return 1.0;
+        }
+        let ray_length = r.direction().length();
+        let mut transmittance = 1.0;
+        let mut t = enter_t;
+        loop {
+            t = self.step(t, ray_length, rng);
+            if t >= leave_t || transmittance <= 0.0 {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density(r.at(t)) / self.majorant;
+        }
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    /// Delta tracking: tentative collisions are sampled as if the medium were
+    /// as dense as the majorant everywhere, each one is real with probability
+    /// density / majorant, the others are passed through.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
+        let Some((enter_t, leave_t)) = span(&self.boundary, r, ray_t, rng) else {
+            return false;
+        };
+        if self.majorant <= 0.0 {
+            return false;
+        }
+        let ray_length = r.direction().length();
+        let mut t = enter_t;
+        loop {
+            t = self.step(t, ray_length, rng);
+            if t >= leave_t {
+                return false;
+            }
+            let density = self.density(r.at(t));
+            if random::number(rng, 0.0, self.majorant) < density {
+                let glow = self.emission * (density / self.majorant);
+                collide(rec, r, t, self.phase_function.clone().with_emission(glow));
+                return true;
+            }
+        }
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Direction;
+
+    /// A unit cube whose density rises linearly from 0 at x = 0 to 2 at x = 1,
+    /// so rays crossing it along x see an optical depth of 1.
+    fn ramp() -> HeterogeneousMedium {
+        let size = 64;
+        // Two voxels along y and z, each of the four rows along x is the same ramp
+        #[allow(clippy::cast_precision_loss)]
+        let densities = (0..4)
+            .flat_map(|_| (0..size).map(|x| 2.0 * (x as f32 + 0.5) / size as f32))
+            .collect();
+        let grid = VoxelGrid::new(
+            [size, 2, 2],
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 1.0),
+            densities,
+        )
+        .unwrap();
+        let fog = Material::isotropic(Texture::solid_color(Color::ONE));
+        HeterogeneousMedium::grid(Arc::new(grid), 1.0, fog, Color::new(0.0, 0.0, 0.0))
+    }
+
+    /// The ray along +x through the middle of the ramp.
+    fn ray() -> Ray {
+        Ray::new(
+            Point::new(-1.0, 0.5, 0.5),
+            Direction::new(1.0, 0.0, 0.0),
+            0.0,
+        )
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn delta_tracking_passes_rays_by_the_optical_depth() {
+        let medium = ramp();
+        let mut rng = Sampler::new(53);
+        let count = 100_000;
+        let passed = (0..count)
+            .filter(|_| {
+                let mut rec = HitRecord::default();
+                !medium.hit(
+                    &ray(),
+                    Interval::new(0.001, f32::INFINITY),
+                    &mut rec,
+                    &mut rng,
+                )
+            })
+            .count();
+        let passed = passed as f32 / count as f32;
+        assert!((passed - (-1.0f32).exp()).abs() < 0.01, "{passed} passed");
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn ratio_tracking_estimates_the_transmittance() {
+        let medium = ramp();
+        let mut rng = Sampler::new(59);
+        let count = 20_000;
+        let sum: f32 = (0..count)
+            .map(|_| medium.transmittance(&ray(), Interval::new(0.001, f32::INFINITY), &mut rng))
+            .sum();
+        let mean = sum / count as f32;
+        assert!((mean - (-1.0f32).exp()).abs() < 0.01, "mean {mean}");
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn overlapping_media_track_independently() {
+        // Two copies of the ramp in the same place add up to an optical depth of 2
+        let medium = ramp();
+        let media = [medium.clone(), medium];
+        let mut rng = Sampler::new(61);
+        let count = 100_000;
+        let passed = (0..count)
+            .filter(|_| {
+                media.iter().all(|medium| {
+                    let mut rec = HitRecord::default();
+                    let ray_t = Interval::new(0.001, f32::INFINITY);
+                    !medium.hit(&ray(), ray_t, &mut rec, &mut rng)
+                })
+            })
+            .count();
+        let passed = passed as f32 / count as f32;
+        assert!((passed - (-2.0f32).exp()).abs() < 0.01, "{passed} passed");
+
+        let objects = media.map(HittableObject::HeterogeneousMedium).to_vec();
+        let bvh = HittableObject::bvh(objects);
+        let sum: f32 = (0..count)
+            .map(|_| bvh.transmittance(&ray(), Interval::new(0.001, f32::INFINITY), &mut rng))
+            .sum();
+        let mean = sum / count as f32;
+        assert!((mean - (-2.0f32).exp()).abs() < 0.01, "mean {mean}");
+    }
+}
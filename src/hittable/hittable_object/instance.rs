@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use glam::{Affine3A, Mat3A, Vec3A};
+
+use super::HittableObject;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, LightSample};
+use crate::interval::Interval;
+use crate::random::Sampler;
+use crate::ray::{Direction, Point, Ray};
+
+/// A shared object placed in the world by an affine transform. Rays are moved
+/// into the object's own space instead of moving the object.
+#[derive(Clone)]
+pub struct Instance {
+    object: Arc<HittableObject>,
+    transform: Affine3A,
+    inverse: Affine3A,
+    normal_matrix: Mat3A, // Inverse transpose of the linear part
+    bbox: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<HittableObject>, transform: Affine3A) -> Self {
+        let inverse = transform.inverse();
+        let normal_matrix = inverse.matrix3.transpose();
+
+        // The world box bounds all eight transformed corners of the object box
+        let object_box = object.bounding_box();
+        let mut min = Point::splat(f32::INFINITY);
+        let mut max = Point::splat(f32::NEG_INFINITY);
+        for corner in 0..8 {
+            let pick = |axis: &Interval, bit| {
+                if corner & bit == 0 {
+                    axis.min()
+                } else {
+                    axis.max()
+                }
+            };
+            let p = Point::new(
+                pick(&object_box.x, 1),
+                pick(&object_box.y, 2),
+                pick(&object_box.z, 4),
+            );
+            let p = transform.transform_point3(p);
+            min = min.min(p);
+            max = max.max(p);
+        }
+
+        Self {
+            object,
+            transform,
+            inverse,
+            normal_matrix,
+            bbox: Aabb::new(min, max),
+        }
+    }
+
+    pub fn object(&self) -> &HittableObject {
+        &self.object
+    }
+
+    /// Another object placed by the same transform.
+    pub fn with_object(&self, object: HittableObject) -> HittableObject {
+        HittableObject::instance(Arc::new(object), self.transform)
+    }
+
+    /// Samples the object in its own space and carries the point into the world.
+    pub fn sample(&self, origin: Point, time: f32, rng: &mut Sampler) -> Option<LightSample> {
+        let object_origin = self.inverse.transform_point3(origin);
+        let sample = self.object.sample_light(object_origin, time, rng)?;
+        self.to_world(origin, object_origin, sample)
+    }
+
+    /// The sample [`Self::sample`] takes to pick `direction`, if it points at the object.
+    pub fn sample_toward(
+        &self,
+        origin: Point,
+        direction: Direction,
+        time: f32,
+    ) -> Option<LightSample> {
+        let object_origin = self.inverse.transform_point3(origin);
+        let object_direction = self.inverse.transform_vector3(direction);
+        let sample = self
+            .object
+            .light_sample_toward(object_origin, object_direction, time)?;
+        self.to_world(origin, object_origin, sample)
+    }
+
+    /// Moves a world ray into the object's space. The direction is not
+    /// renormalized, so t means the same in both spaces.
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.transform_point3(r.origin()),
+            self.inverse.transform_vector3(r.direction()),
+            r.time(),
+        )
+    }
+
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Sampler) -> f32 {
+        self.object.transmittance(&self.object_ray(r), ray_t, rng)
+    }
+
+    /// Scaling changes both the area and the solid angle a light covers, so
+    /// the density is carried over as a density per unit area.
+    fn to_world(
+        &self,
+        origin: Point,
+        object_origin: Point,
+        sample: LightSample,
+    ) -> Option<LightSample> {
+        let offset = sample.point - object_origin;
+        let cosine = sample.normal.dot(offset).abs() / offset.length();
+        let object_area_pdf = sample.pdf * cosine / offset.length_squared();
+
+        // A unit of object area becomes |det| * |M^-T n| units of world area
+        let normal = self.normal_matrix * Vec3A::from(sample.normal);
+        let area_scale = self.transform.matrix3.determinant().abs() * normal.length();
+        LightSample::from_area(
+            origin,
+            self.transform.transform_point3(sample.point),
+            normal.normalize().into(),
+            object_area_pdf / area_scale,
+        )
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
+        if !self.object.hit(&self.object_ray(r), ray_t, rec, rng) {
+            return false;
+        }
+
+        // The inverse transpose keeps normals perpendicular to the surface and
+        // the side they face relative to the ray
+        rec.p = self.transform.transform_point3(rec.p);
+        rec.normal = (self.normal_matrix * Vec3A::from(rec.normal))
+            .normalize()
+            .into();
+        true
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+}
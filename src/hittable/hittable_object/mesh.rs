@@ -0,0 +1,38 @@
+use super::bvh::Bvh;
+use super::triangle::MeshData;
+use super::HittableObject;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::random::Sampler;
+use crate::ray::Ray;
+
+/// An indexed triangle mesh. The triangles all point into one shared
+/// [`MeshData`] and are kept in a BVH of their own.
+#[derive(Clone)]
+pub struct Mesh {
+    bvh: Bvh,
+}
+
+impl Mesh {
+    pub fn new(data: MeshData) -> Self {
+        Self {
+            bvh: Bvh::new(HittableObject::mesh_triangles(data)),
+        }
+    }
+
+    pub fn collect_lights(&self, lights: &mut Vec<HittableObject>) {
+        for triangle in self.bvh.objects() {
+            triangle.collect_lights(lights);
+        }
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord, rng: &mut Sampler) -> bool {
+        self.bvh.hit(r, ray_t, rec, rng)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.bvh.bounding_box()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, LightSample};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Point, Ray};
+
+/// A parallelogram with corner `q` and edges `u` and `v`.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    q: Point,
+    u: Direction,
+    v: Direction,
+    w: Direction, // Cached n / (n . n), turns plane offsets into (alpha, beta) coordinates
+    normal: Direction,
+    d: f32, // Plane equation: normal . p = d
+    mat: Material,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Point, u: Direction, v: Direction, mat: Material, bbox: Aabb) -> Self {
+        let n = u.cross(v);
+        let normal = n.normalize();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            mat,
+            bbox,
+        }
+    }
+
+    pub const fn material(&self) -> &Material {
+        &self.mat
+    }
+
+    /// Samples a point uniformly over the area of the quad.
+    pub fn sample(&self, origin: Point, rng: &mut Sampler) -> Option<LightSample> {
+        let point = self.q
+            + self.u * random::number(rng, 0.0, 1.0)
+            + self.v * random::number(rng, 0.0, 1.0);
+        let area = self.u.cross(self.v).length();
+        LightSample::from_area(origin, point, self.normal, 1.0 / area)
+    }
+
+    /// The sample [`Self::sample`] takes to pick `direction`, if it points at the quad.
+    pub fn sample_toward(&self, origin: Point, direction: Direction) -> Option<LightSample> {
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(origin, direction, 0.0);
+        if !self.intersect(&ray, Interval::new(0.001, f32::INFINITY), &mut rec) {
+            return None;
+        }
+        let area = self.u.cross(self.v).length();
+        LightSample::from_area(origin, rec.p, self.normal, 1.0 / area)
+    }
+
+    fn is_interior(alpha: f32, beta: f32, rec: &mut HitRecord) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+        // Given the hit point in plane coordinates, return false if it is outside the
+        // primitive, otherwise set the hit record UV coordinates and return true.
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return false;
+        }
+        rec.u = alpha;
+        rec.v = beta;
+        true
+    }
+}
+
+impl Quad {
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = self.normal.dot(ray.direction());
+
+        // No hit if the ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // Return false if the hit point parameter t is outside the ray interval
+        let t = (self.d - self.normal.dot(ray.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // Determine if the hit point lies within the planar shape using its plane coordinates
+        let intersection = ray.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = self.w.dot(planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hitpt_vector));
+
+        if !Self::is_interior(alpha, beta, rec) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(ray, &self.normal);
+
+        true
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, _rng: &mut Sampler) -> bool {
+        self.intersect(ray, ray_t, rec)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+}
@@ -1,7 +1,10 @@
+use std::f32::consts::{PI, TAU};
+
 use crate::aabb::Aabb;
-use crate::hittable::{HitRecord, Hittable};
+use crate::hittable::{HitRecord, Hittable, LightSample};
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::random::{self, Sampler};
 use crate::ray::{Direction, Point, Ray};
 
 #[derive(Debug, Clone)]
@@ -30,17 +33,84 @@ impl Sphere {
         self.center1 + self.center_vec * time
     }
 
+    pub const fn material(&self) -> &Material {
+        &self.mat
+    }
+
+    /// Samples a direction inside the cone the sphere covers as seen from
+    /// `origin`, or a point on its whole surface when `origin` is inside.
+    pub fn sample(&self, origin: Point, time: f32, rng: &mut Sampler) -> Option<LightSample> {
+        let center = self.center(time);
+        let Some((direction, pdf)) = random::sphere_from_point(rng, center, self.radius, origin)
+        else {
+            let (normal, _) = random::uniform_sphere(rng);
+            let area = 2.0 * TAU * self.radius * self.radius;
+            return LightSample::from_area(
+                origin,
+                center + normal * self.radius,
+                normal,
+                1.0 / area,
+            );
+        };
+
+        // Distance to the near side of the sphere along the sampled direction
+        let offset = center - origin;
+        let along = offset.dot(direction);
+        let across_squared = along.mul_add(-along, offset.length_squared());
+        let t = along
+            - self
+                .radius
+                .mul_add(self.radius, -across_squared)
+                .max(0.0)
+                .sqrt();
+        let point = origin + direction * t;
+
+        Some(LightSample {
+            point,
+            normal: (point - center) / self.radius,
+            pdf,
+        })
+    }
+
+    /// The sample [`Self::sample`] takes to pick `direction`, if it points at the sphere.
+    pub fn sample_toward(
+        &self,
+        origin: Point,
+        direction: Direction,
+        time: f32,
+    ) -> Option<LightSample> {
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(origin, direction, time);
+        if !self.intersect(&ray, Interval::new(0.001, f32::INFINITY), &mut rec) {
+            return None;
+        }
+        let center = self.center(time);
+        let normal = (rec.p - center) / self.radius;
+        let distance_squared = (center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+
+        if distance_squared <= radius_squared {
+            let area = 2.0 * TAU * radius_squared;
+            return LightSample::from_area(origin, rec.p, normal, 1.0 / area);
+        }
+        Some(LightSample {
+            point: rec.p,
+            normal,
+            pdf: 1.0 / (TAU * random::cone_height(radius_squared / distance_squared)),
+        })
+    }
+
     fn get_sphere_uv(p: &Point, u: &mut f32, v: &mut f32) {
         let theta = f32::acos(-p.y);
-        let phi = f32::atan2(-p.z, p.x) + std::f32::consts::PI;
+        let phi = f32::atan2(-p.z, p.x) + PI;
 
-        *u = phi / std::f32::consts::TAU;
-        *v = theta / std::f32::consts::PI;
+        *u = phi / TAU;
+        *v = theta / PI;
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+impl Sphere {
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
         let center = if self.is_moving {
             self.center(ray.time())
         } else {
@@ -77,6 +147,12 @@ impl Hittable for Sphere {
 
         true
     }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, _rng: &mut Sampler) -> bool {
+        self.intersect(ray, ray_t, rec)
+    }
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
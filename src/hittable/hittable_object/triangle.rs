@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use glam::Vec2;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, LightSample};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Point, Ray};
+
+/// Vertex and index buffers shared by every triangle of a mesh. `normals` and
+/// `uvs` are either empty or hold one entry per position.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Point>,
+    pub normals: Vec<Direction>,
+    pub uvs: Vec<Vec2>,
+    pub indices: Vec<[u32; 3]>,
+    pub materials: Vec<Material>,
+    pub material_ids: Vec<u32>, // Index into `materials` per triangle, empty uses the first
+}
+
+/// What triangles without a material in `MeshData::materials` are made of,
+/// the same as `Material::default()`.
+static FALLBACK_MATERIAL: Material = Material::dielectric(1.5);
+
+impl MeshData {
+    pub const fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn vertices(&self, triangle: usize) -> [usize; 3] {
+        self.indices[triangle].map(|i| i as usize)
+    }
+
+    fn material(&self, triangle: usize) -> &Material {
+        let id = self.material_ids.get(triangle).map_or(0, |&id| id as usize);
+        self.materials.get(id).unwrap_or(&FALLBACK_MATERIAL)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<MeshData>, index: usize) -> Self {
+        let [a, b, c] = mesh.vertices(index).map(|i| mesh.positions[i]);
+        let bbox = Aabb::new_with_boxes(&Aabb::new(a, b), &Aabb::new(c, c)).pad_to_minimums();
+        Self { mesh, index, bbox }
+    }
+
+    pub fn material(&self) -> &Material {
+        self.mesh.material(self.index)
+    }
+
+    /// Samples a point uniformly over the area of the triangle.
+    pub fn sample(&self, origin: Point, rng: &mut Sampler) -> Option<LightSample> {
+        let [a, b, c] = self
+            .mesh
+            .vertices(self.index)
+            .map(|i| self.mesh.positions[i]);
+        let (point, area_pdf) = random::triangle(rng, [a, b, c]);
+        let normal = (b - a).cross(c - a).normalize();
+        LightSample::from_area(origin, point, normal, area_pdf)
+    }
+
+    /// The sample [`Self::sample`] takes to pick `direction`, if it points at the triangle.
+    pub fn sample_toward(&self, origin: Point, direction: Direction) -> Option<LightSample> {
+        let [a, b, c] = self
+            .mesh
+            .vertices(self.index)
+            .map(|i| self.mesh.positions[i]);
+        let (t, _) = intersect(&Ray::new(origin, direction, 0.0), [a, b, c])?;
+        if t <= 0.001 {
+            return None;
+        }
+        let cross = (b - a).cross(c - a);
+        let area = 0.5 * cross.length();
+        LightSample::from_area(
+            origin,
+            origin + direction * t,
+            cross / (2.0 * area),
+            1.0 / area,
+        )
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, _rng: &mut Sampler) -> bool {
+        let vertices = self.mesh.vertices(self.index);
+        let [a, b, c] = vertices.map(|i| self.mesh.positions[i]);
+        let Some((t, barycentric)) = intersect(ray, [a, b, c]) else {
+            return false;
+        };
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = ray.at(t);
+        let geometric_normal = (b - a).cross(c - a).normalize();
+        rec.set_face_normal(ray, &geometric_normal);
+
+        // Smooth shading keeps the side chosen by the geometric normal
+        if !self.mesh.normals.is_empty() {
+            let shading_normal = vertices
+                .iter()
+                .zip(barycentric)
+                .map(|(&i, weight)| self.mesh.normals[i] * weight)
+                .sum::<Direction>()
+                .normalize_or_zero();
+            if shading_normal != Direction::ZERO {
+                rec.normal = if rec.front_face {
+                    shading_normal
+                } else {
+                    -shading_normal
+                };
+            }
+        }
+
+        let uv = if self.mesh.uvs.is_empty() {
+            Vec2::new(barycentric[1], barycentric[2])
+        } else {
+            vertices
+                .iter()
+                .zip(barycentric)
+                .map(|(&i, weight)| self.mesh.uvs[i] * weight)
+                .sum()
+        };
+        rec.u = uv.x;
+        rec.v = uv.y;
+        rec.mat = self.mesh.material(self.index).clone();
+
+        true
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+}
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald, JCGT 2013).
+/// Returns the ray parameter and the barycentric weights of the three vertices.
+#[allow(clippy::cast_possible_truncation, clippy::suboptimal_flops)]
+fn intersect(ray: &Ray, vertices: [Point; 3]) -> Option<(f32, [f32; 3])> {
+    let dir = ray.direction();
+
+    // Permute the axes so the ray travels mostly along z
+    let abs_dir = dir.abs();
+    let kz = if abs_dir.x > abs_dir.y && abs_dir.x > abs_dir.z {
+        0
+    } else if abs_dir.y > abs_dir.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear so the ray points straight down +z, then test the edges in 2D
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+    let [p0, p1, p2] = vertices.map(|v| v - ray.origin());
+    let shear = |p: Point| (sx.mul_add(-p[kz], p[kx]), sy.mul_add(-p[kz], p[ky]));
+    let (q0, q1, q2) = (shear(p0), shear(p1), shear(p2));
+
+    // Signed edge functions, each one is the weight of the opposite vertex. No fused
+    // multiply-add here: a shared edge must give exactly opposite values for both
+    // triangles, otherwise rays can slip through the crack between them.
+    let edge = |(px, py): (f32, f32), (qx, qy): (f32, f32)| px * qy - py * qx;
+    let mut e0 = edge(q2, q1);
+    let mut e1 = edge(q0, q2);
+    let mut e2 = edge(q1, q0);
+
+    // Fall back to double precision when an edge passes exactly through the ray
+    if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+        let edge = |(px, py): (f32, f32), (qx, qy): (f32, f32)| {
+            (f64::from(px) * f64::from(qy) - f64::from(py) * f64::from(qx)) as f32
+        };
+        e0 = edge(q2, q1);
+        e1 = edge(q0, q2);
+        e2 = edge(q1, q0);
+    }
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    let det = e0 + e1 + e2;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = sz * e2.mul_add(p2[kz], e0.mul_add(p0[kz], e1 * p1[kz])) / det;
+    Some((t, [e0 / det, e1 / det, e2 / det]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{HittableObject, LightList};
+
+    #[test]
+    fn meshes_without_materials_fall_back_to_the_default() {
+        let data = MeshData {
+            positions: vec![Point::ZERO, Point::X, Point::Y],
+            indices: vec![[0, 1, 2]],
+            material_ids: vec![3],
+            ..MeshData::default()
+        };
+        let triangle = Triangle::new(Arc::new(data.clone()), 0);
+        assert!(matches!(triangle.material(), Material::Dielectric(_)));
+        let lights = LightList::new(&HittableObject::mesh(data));
+        assert!(lights.sample(Point::Z, 0.0, &mut Sampler::new(1)).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::hittable::HittableObject;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Point};
+
+/// A point picked on a light, as seen from the point being shaded.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub point: Point,
+    pub normal: Direction, // Outward geometric normal of the light at `point`
+    pub pdf: f32,          // Probability density per unit solid angle around `origin`
+}
+
+impl LightSample {
+    /// Converts a density per unit area on the light into one per unit solid
+    /// angle at `origin`, `None` when the light is seen exactly edge on.
+    pub fn from_area(
+        origin: Point,
+        point: Point,
+        normal: Direction,
+        area_pdf: f32,
+    ) -> Option<Self> {
+        let offset = point - origin;
+        let distance_squared = offset.length_squared();
+        let cosine = normal.dot(offset).abs() / distance_squared.sqrt();
+        (cosine > 1e-6 && distance_squared > 0.0).then(|| Self {
+            point,
+            normal,
+            pdf: area_pdf * distance_squared / cosine,
+        })
+    }
+}
+
+/// Every emissive sphere, quad and triangle of a scene, so the integrator can
+/// aim rays at lights instead of waiting to hit them by chance.
+pub struct LightList {
+    lights: Vec<HittableObject>,
+}
+
+impl LightList {
+    pub fn new(world: &HittableObject) -> Self {
+        let mut lights = Vec::new();
+        world.collect_lights(&mut lights);
+        Self { lights }
+    }
+
+    /// Picks one light uniformly and samples a point on it. The density
+    /// includes the chance of picking that light.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn sample(&self, origin: Point, time: f32, rng: &mut Sampler) -> Option<LightSample> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let light = &self.lights[random::number(rng, 0, self.lights.len())];
+        let mut sample = light.sample_light(origin, time, rng)?;
+        sample.pdf /= self.lights.len() as f32;
+        Some(sample)
+    }
+
+    /// Density with which [`Self::sample`] picks `direction` from `origin` and
+    /// reaches the light it first runs into. Lights behind that one are hidden
+    /// from it, so their samples along `direction` never arrive.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn pdf(&self, origin: Point, direction: Direction, time: f32) -> f32 {
+        let nearest = self
+            .lights
+            .iter()
+            .filter_map(|light| light.light_sample_toward(origin, direction, time))
+            .min_by(|a, b| {
+                let distance = |sample: &LightSample| (sample.point - origin).length_squared();
+                distance(a).total_cmp(&distance(b))
+            });
+        nearest.map_or(0.0, |sample| sample.pdf / self.lights.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Material;
+
+    #[test]
+    fn only_the_nearest_light_along_a_direction_counts() {
+        // Two 2x2 lights straight above the origin, the far one hidden behind the near one
+        let light = |z| {
+            HittableObject::quad(
+                Point::new(-1.0, -1.0, z),
+                Direction::new(2.0, 0.0, 0.0),
+                Direction::new(0.0, 2.0, 0.0),
+                Material::diffuse_light(Color::ONE),
+            )
+        };
+        let (near, far) = (light(1.0), light(2.0));
+        let lights = LightList::new(&HittableObject::bvh(vec![far, near.clone()]));
+
+        let (origin, up) = (Point::ZERO, Direction::Z);
+        let near_pdf = near.light_sample_toward(origin, up, 0.0).unwrap().pdf;
+        let pdf = lights.pdf(origin, up, 0.0);
+        assert!(
+            (pdf - near_pdf / 2.0).abs() < 1e-6,
+            "{pdf}, near light {near_pdf}"
+        );
+    }
+}
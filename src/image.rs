@@ -0,0 +1,106 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::color::{self, Color};
+
+mod hdr;
+mod pfm;
+mod png;
+mod ppm;
+mod zlib;
+
+/// A rectangular buffer of linear colors, stored row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+        }
+    }
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, c: Color) {
+        self.pixels[y * self.width + x] = c;
+    }
+
+    /// Reads an image, choosing the format from the extension: high dynamic
+    /// range `.pfm` or Radiance `.hdr`, or 8/16-bit `.png` or `.ppm`. The 8/16-bit
+    /// formats are taken to be sRGB encoded and are decoded to linear values.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::load_with_encoding(path, true)
+    }
+
+    /// Like [`Image::load`], but keeps the stored values of `.png` and `.ppm`
+    /// files as they are, for images that hold data rather than colors.
+    pub fn load_linear(path: &Path) -> io::Result<Self> {
+        Self::load_with_encoding(path, false)
+    }
+
+    fn load_with_encoding(path: &Path, srgb: bool) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let mut image = match extension.as_deref() {
+            Some("pfm") => pfm::read(&data)?,
+            Some("hdr") => hdr::read(&data)?,
+            Some("png") => png::read(&data)?,
+            Some("ppm") => ppm::read(&data)?,
+            _ => return Err(invalid_data("unsupported image format")),
+        };
+        if image.pixels.is_empty() {
+            return Err(invalid_data("image has no pixels"));
+        }
+        if srgb && matches!(extension.as_deref(), Some("png" | "ppm")) {
+            for pixel in &mut image.pixels {
+                *pixel = Color::from_array(pixel.to_array().map(color::srgb_to_linear));
+            }
+        }
+        Ok(image)
+    }
+
+    /// Gamma encodes every pixel into packed 8-bit RGB triples.
+    fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&c| color::to_rgb8(c))
+            .collect()
+    }
+
+    /// Writes the image to `path`, choosing the format from the extension.
+    /// `.ppm` files are written as binary P6, everything else as PNG.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let is_ppm = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ppm"));
+
+        let mut out = BufWriter::new(File::create(path)?);
+        if is_ppm {
+            ppm::write(&mut out, self.width, self.height, &self.to_rgb8())?;
+        } else {
+            png::write(&mut out, self.width, self.height, &self.to_rgb8())?;
+        }
+        out.flush()
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
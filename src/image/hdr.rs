@@ -0,0 +1,109 @@
+use std::io;
+
+use super::{invalid_data, Image};
+use crate::color::Color;
+
+/// Reads a Radiance RGBE (`.hdr`) image, flat or with run-length encoded scanlines.
+pub fn read(data: &[u8]) -> io::Result<Image> {
+    let mut lines = data.split(|&b| b == b'\n');
+    let magic = lines.next().unwrap_or_default();
+    if !magic.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+    let mut offset = magic.len() + 1;
+
+    // Header variables run until the first empty line
+    for line in lines.by_ref() {
+        offset += line.len() + 1;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix(b"FORMAT=") {
+            if format != b"32-bit_rle_rgbe" {
+                return Err(invalid_data("unsupported HDR pixel format"));
+            }
+        }
+    }
+
+    let resolution = lines
+        .next()
+        .and_then(|l| std::str::from_utf8(l).ok())
+        .ok_or_else(|| invalid_data("missing HDR resolution"))?;
+    offset += resolution.len() + 1;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (w.parse(), h.parse()),
+        _ => return Err(invalid_data("unsupported HDR orientation")),
+    };
+    let (Ok(width), Ok(height)) = (width, height) else {
+        return Err(invalid_data("bad HDR resolution"));
+    };
+
+    let mut image = Image::new(width, height);
+    let mut pixels = &data[offset.min(data.len())..];
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        pixels = read_scanline(pixels, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set(x, y, rgbe_to_color(*rgbe));
+        }
+    }
+    Ok(image)
+}
+
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> io::Result<&'a [u8]> {
+    let truncated = || invalid_data("truncated HDR data");
+    let width = scanline.len();
+
+    let is_rle = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && usize::from(data[2]) << 8 | usize::from(data[3]) == width;
+    if !is_rle {
+        let flat = data.get(..width * 4).ok_or_else(truncated)?;
+        for (pixel, rgbe) in scanline.iter_mut().zip(flat.chunks_exact(4)) {
+            pixel.copy_from_slice(rgbe);
+        }
+        return Ok(&data[width * 4..]);
+    }
+
+    // Each of the four channels is run-length encoded separately
+    let mut pos = 4;
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = usize::from(*data.get(pos).ok_or_else(truncated)?);
+            pos += 1;
+            if count > 128 {
+                let run = count - 128;
+                let value = *data.get(pos).ok_or_else(truncated)?;
+                pos += 1;
+                for pixel in scanline.get_mut(x..x + run).ok_or_else(truncated)? {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                let values = data.get(pos..pos + count).ok_or_else(truncated)?;
+                pos += count;
+                for (pixel, &value) in scanline
+                    .get_mut(x..x + count)
+                    .ok_or_else(truncated)?
+                    .iter_mut()
+                    .zip(values)
+                {
+                    pixel[channel] = value;
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(&data[pos..])
+}
+
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2f32.powi(i32::from(e) - (128 + 8));
+    Color::new(f32::from(r), f32::from(g), f32::from(b)) * scale
+}
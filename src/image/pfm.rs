@@ -0,0 +1,67 @@
+use std::io;
+
+use super::{invalid_data, Image};
+use crate::color::Color;
+
+/// Reads a Portable Float Map, either color (`PF`) or greyscale (`Pf`).
+pub fn read(data: &[u8]) -> io::Result<Image> {
+    let mut header = data.splitn(4, u8::is_ascii_whitespace);
+    let mut field = || {
+        header
+            .next()
+            .and_then(|f| std::str::from_utf8(f).ok())
+            .ok_or_else(|| invalid_data("truncated PFM header"))
+    };
+
+    let channels = match field()? {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file")),
+    };
+    let width: usize = field()?
+        .parse()
+        .map_err(|_| invalid_data("bad PFM width"))?;
+    let height: usize = field()?
+        .parse()
+        .map_err(|_| invalid_data("bad PFM height"))?;
+    let rest = header.next().unwrap_or_default();
+
+    // The scale line ends the header; its sign gives the byte order
+    let scale_end = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| invalid_data("truncated PFM header"))?;
+    let scale: f32 = std::str::from_utf8(&rest[..scale_end])
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .ok_or_else(|| invalid_data("bad PFM scale"))?;
+    let little_endian = scale < 0.0;
+
+    let floats = &rest[scale_end + 1..];
+    if floats.len() < width * height * channels * 4 {
+        return Err(invalid_data("truncated PFM data"));
+    }
+    let value = |index: usize| {
+        let bytes = floats[index * 4..index * 4 + 4].try_into().unwrap();
+        if little_endian {
+            f32::from_le_bytes(bytes)
+        } else {
+            f32::from_be_bytes(bytes)
+        }
+    };
+
+    let mut image = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            // Rows are stored from the bottom of the image up
+            let i = ((height - 1 - y) * width + x) * channels;
+            let c = if channels == 3 {
+                Color::new(value(i), value(i + 1), value(i + 2))
+            } else {
+                Color::splat(value(i))
+            };
+            image.set(x, y, c);
+        }
+    }
+    Ok(image)
+}
@@ -0,0 +1,443 @@
+use std::io::{self, Write};
+
+use super::{invalid_data, zlib, Image};
+use crate::color::Color;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Writes packed 8-bit RGB data as a truecolor PNG.
+pub fn write(out: &mut impl Write, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image too large for PNG");
+    let width_be = u32::try_from(width).map_err(|_| too_large())?.to_be_bytes();
+    let height_be = u32::try_from(height)
+        .map_err(|_| too_large())?
+        .to_be_bytes();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width_be);
+    header.extend_from_slice(&height_be);
+    // Bit depth 8, color type 2 (RGB), default compression, filter and interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    out.write_all(&SIGNATURE)?;
+    write_chunk(out, *b"IHDR", &header)?;
+    write_chunk(out, *b"IDAT", &zlib::compress(&filter(width, rgb)))?;
+    write_chunk(out, *b"IEND", &[])
+}
+
+fn write_chunk(out: &mut impl Write, kind: [u8; 4], data: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "PNG chunk too large"))?;
+    out.write_all(&length.to_be_bytes())?;
+    out.write_all(&kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc32(&[&kind, data]).to_be_bytes())
+}
+
+/// Bytes per pixel of the RGB images written.
+const BPP: usize = 3;
+
+/// Prefixes every scanline with the filter type that gives the smallest sum of
+/// absolute residuals, the usual heuristic from the PNG specification.
+fn filter(width: usize, rgb: &[u8]) -> Vec<u8> {
+    let stride = width * BPP;
+    let mut filtered = Vec::with_capacity(rgb.len() + rgb.len() / stride.max(1));
+    let zero_row = vec![0u8; stride];
+    let mut candidate = vec![0u8; stride];
+    let mut best = vec![0u8; stride];
+
+    for (y, row) in rgb.chunks(stride).enumerate() {
+        let above = if y == 0 {
+            &zero_row[..]
+        } else {
+            &rgb[(y - 1) * stride..y * stride]
+        };
+
+        let mut best_type = 0;
+        let mut best_cost = u64::MAX;
+        for filter_type in 0..5u8 {
+            filter_row(filter_type, row, above, &mut candidate);
+            let cost = candidate
+                .iter()
+                .map(|&v| u64::from(v.cast_signed().unsigned_abs()))
+                .sum();
+            if cost < best_cost {
+                best_cost = cost;
+                best_type = filter_type;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        filtered.push(best_type);
+        filtered.extend_from_slice(&best);
+    }
+    filtered
+}
+
+/// Writes the residuals of `row` under `filter_type` to `out`.
+fn filter_row(filter_type: u8, row: &[u8], above: &[u8], out: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= BPP { row[i - BPP] } else { 0 };
+        let b = above[i];
+        let c = if i >= BPP { above[i - BPP] } else { 0 };
+        let predictor = match filter_type {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => u8::midpoint(a, b),
+            _ => paeth(a, b, c),
+        };
+        out[i] = row[i].wrapping_sub(predictor);
+    }
+}
+
+/// Adam7 passes: starting column and row, then column and row spacing.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Reads a PNG of any standard color type and bit depth, interlaced or not.
+/// Samples are scaled to [0, 1] but left in the file's encoding, and alpha is dropped.
+pub fn read(data: &[u8]) -> io::Result<Image> {
+    let mut rest = data
+        .strip_prefix(&SIGNATURE)
+        .ok_or_else(|| invalid_data("not a PNG file"))?;
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut compressed = Vec::new();
+    loop {
+        let (kind, chunk, after) = next_chunk(rest)?;
+        rest = after;
+        match &kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // Lowercase first letter: ancillary, safe to skip
+            _ if kind[0].is_ascii_lowercase() => {}
+            _ => return Err(invalid_data("unsupported critical PNG chunk")),
+        }
+    }
+    let header = header.ok_or_else(|| invalid_data("missing PNG header"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(invalid_data("missing PNG palette"));
+    }
+
+    let raw = zlib::decompress(&compressed)?;
+    let mut image = Image::new(header.width, header.height);
+    let passes: &[_] = if header.interlaced {
+        &ADAM7
+    } else {
+        &[(0, 0, 1, 1)]
+    };
+    let mut offset = 0;
+    for &(x0, y0, dx, dy) in passes {
+        let pass_width = (header.width + dx - 1 - x0.min(header.width)) / dx;
+        let pass_height = (header.height + dy - 1 - y0.min(header.height)) / dy;
+        if pass_width == 0 || pass_height == 0 {
+            continue;
+        }
+        let stride = (pass_width * header.bits_per_pixel()).div_ceil(8);
+        let size = (stride + 1) * pass_height;
+        let pass = raw
+            .get(offset..offset + size)
+            .ok_or_else(|| invalid_data("truncated PNG image data"))?;
+        offset += size;
+
+        let rows = unfilter(pass, stride, header.bits_per_pixel().div_ceil(8))?;
+        for (j, row) in rows.chunks(stride).enumerate() {
+            for i in 0..pass_width {
+                let color = header.color(row, i, palette)?;
+                image.set(x0 + i * dx, y0 + j * dy, color);
+            }
+        }
+    }
+    Ok(image)
+}
+
+fn next_chunk(data: &[u8]) -> io::Result<([u8; 4], &[u8], &[u8])> {
+    let truncated = || invalid_data("truncated PNG chunk");
+    let length = data.get(..4).ok_or_else(truncated)?;
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let body = data.get(4..8 + length).ok_or_else(truncated)?;
+    let crc = data.get(8 + length..12 + length).ok_or_else(truncated)?;
+    if crc32(&[body]).to_be_bytes() != crc {
+        return Err(invalid_data("PNG chunk checksum mismatch"));
+    }
+    let kind = [body[0], body[1], body[2], body[3]];
+    Ok((kind, &body[4..], &data[12 + length..]))
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> io::Result<Self> {
+        let &[w0, w1, w2, w3, h0, h1, h2, h3, bit_depth, color_type, compression, filter, interlace] =
+            chunk
+        else {
+            return Err(invalid_data("bad PNG header"));
+        };
+        let valid_depths: &[u8] = match color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            _ => return Err(invalid_data("bad PNG color type")),
+        };
+        if !valid_depths.contains(&bit_depth) || compression != 0 || filter != 0 || interlace > 1 {
+            return Err(invalid_data("unsupported PNG format"));
+        }
+        let header = Self {
+            width: u32::from_be_bytes([w0, w1, w2, w3]) as usize,
+            height: u32::from_be_bytes([h0, h1, h2, h3]) as usize,
+            bit_depth: usize::from(bit_depth),
+            color_type,
+            interlaced: interlace == 1,
+        };
+        if header.width == 0 || header.height == 0 {
+            return Err(invalid_data("PNG image has no pixels"));
+        }
+        Ok(header)
+    }
+
+    const fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    const fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth
+    }
+
+    /// The raw value of one channel of pixel `i` in an unfiltered row.
+    fn sample(&self, row: &[u8], i: usize, channel: usize) -> u16 {
+        let index = i * self.channels() + channel;
+        match self.bit_depth {
+            16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]),
+            8 => u16::from(row[index]),
+            depth => {
+                // Sub-byte samples are packed from the most significant bit
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                u16::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+            }
+        }
+    }
+
+    fn color(&self, row: &[u8], i: usize, palette: &[u8]) -> io::Result<Color> {
+        let max = f32::from(u16::MAX >> (16 - self.bit_depth));
+        let value = |channel| f32::from(self.sample(row, i, channel)) / max;
+        Ok(match self.color_type {
+            0 | 4 => Color::splat(value(0)),
+            3 => {
+                let index = usize::from(self.sample(row, i, 0));
+                let rgb = palette
+                    .get(3 * index..3 * index + 3)
+                    .ok_or_else(|| invalid_data("PNG palette index out of range"))?;
+                Color::new(f32::from(rgb[0]), f32::from(rgb[1]), f32::from(rgb[2])) / 255.0
+            }
+            _ => Color::new(value(0), value(1), value(2)),
+        })
+    }
+}
+
+/// Undoes the per-scanline filters of one image or interlace pass.
+fn unfilter(data: &[u8], stride: usize, bpp: usize) -> io::Result<Vec<u8>> {
+    let mut rows = vec![0u8; data.len() / (stride + 1) * stride];
+    for (y, line) in data.chunks(stride + 1).enumerate() {
+        let (current, previous) = {
+            let (done, todo) = rows.split_at_mut(y * stride);
+            let previous = if y == 0 {
+                None
+            } else {
+                Some(&done[(y - 1) * stride..])
+            };
+            (&mut todo[..stride], previous)
+        };
+        let filter_type = line[0];
+        for i in 0..stride {
+            let a = if i >= bpp { current[i - bpp] } else { 0 };
+            let b = previous.map_or(0, |row| row[i]);
+            let c = if i >= bpp {
+                previous.map_or(0, |row| row[i - bpp])
+            } else {
+                0
+            };
+            let predictor = match filter_type {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => u8::midpoint(a, b),
+                4 => paeth(a, b, c),
+                _ => return Err(invalid_data("bad PNG filter type")),
+            };
+            current[i] = line[i + 1].wrapping_add(predictor);
+        }
+    }
+    Ok(rows)
+}
+
+pub fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let pa = (p - i16::from(a)).abs();
+    let pb = (p - i16::from(b)).abs();
+    let pc = (p - i16::from(c)).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, RngCore};
+
+    use super::*;
+    use crate::random::Sampler;
+
+    /// The 8-bit value a decoded sample was read from.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn byte(value: f32) -> u8 {
+        (value * 255.0).round() as u8
+    }
+
+    fn assert_pixels(image: &Image, width: usize, rgb: &[u8]) {
+        assert_eq!(image.width() * image.height() * 3, rgb.len());
+        for (i, expected) in rgb.chunks(3).enumerate() {
+            let color = image.get(i % width, i / width);
+            let found = [byte(color.x), byte(color.y), byte(color.z)];
+            assert_eq!(found, expected, "pixel {i} of a {width} wide image");
+        }
+    }
+
+    /// Noise, or smooth ramps that favor the predicting filters.
+    #[allow(clippy::cast_possible_truncation)]
+    fn random_image(rng: &mut Sampler, width: usize, height: usize) -> Vec<u8> {
+        let mut rgb = vec![0; width * height * 3];
+        if rng.gen() {
+            rng.fill_bytes(&mut rgb);
+        } else {
+            let (dx, dy): (u8, u8) = (rng.gen_range(0..8), rng.gen_range(0..8));
+            for (i, value) in rgb.iter_mut().enumerate() {
+                let (x, y) = ((i / 3 % width) as u8, (i / 3 / width) as u8);
+                *value = x.wrapping_mul(dx).wrapping_add(y.wrapping_mul(dy)) ^ (i % 3) as u8;
+            }
+        }
+        rgb
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn written_images_read_back_unchanged() {
+        let mut rng = Sampler::new(11);
+        let mut filters_used = [false; 5];
+        for (width, height) in [(1, 1), (1, 9), (9, 1), (7, 5), (33, 20), (64, 64)] {
+            for _ in 0..4 {
+                let rgb = random_image(&mut rng, width, height);
+                for row in filter(width, &rgb).chunks(width * BPP + 1) {
+                    filters_used[usize::from(row[0])] = true;
+                }
+                let mut png = Vec::new();
+                write(&mut png, width, height, &rgb).unwrap();
+                assert_pixels(&read(&png).unwrap(), width, &rgb);
+            }
+        }
+        assert_eq!(filters_used, [true; 5]);
+    }
+
+    #[test]
+    fn every_filter_type_is_undone() {
+        let mut rng = Sampler::new(13);
+        let (width, height) = (6, 4);
+        let stride = width * BPP;
+        let rgb = random_image(&mut rng, width, height);
+        for filter_type in 0..5 {
+            let mut filtered = Vec::new();
+            let mut residuals = vec![0; stride];
+            for (y, row) in rgb.chunks(stride).enumerate() {
+                let above = if y == 0 {
+                    vec![0; stride]
+                } else {
+                    rgb[(y - 1) * stride..y * stride].to_vec()
+                };
+                filter_row(filter_type, row, &above, &mut residuals);
+                filtered.push(filter_type);
+                filtered.extend_from_slice(&residuals);
+            }
+            assert_eq!(
+                unfilter(&filtered, stride, BPP).unwrap(),
+                rgb,
+                "filter {filter_type}"
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn interlaced_palette_images_are_read() {
+        // 5x3 pixels of 4-bit palette indices, stored in the seven Adam7 passes
+        let (width, height) = (5, 3);
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let index = |x: usize, y: usize| ((x + 2 * y) % 4) as u8;
+        let mut raw = Vec::new();
+        for (x0, y0, dx, dy) in ADAM7 {
+            for y in (y0..height).step_by(dy) {
+                let indices: Vec<u8> = (x0..width).step_by(dx).map(|x| index(x, y)).collect();
+                if indices.is_empty() {
+                    break;
+                }
+                raw.push(0);
+                raw.extend(
+                    indices
+                        .chunks(2)
+                        .map(|pair| pair[0] << 4 | pair.get(1).unwrap_or(&0)),
+                );
+            }
+        }
+
+        let mut png = SIGNATURE.to_vec();
+        let header = [0, 0, 0, 5, 0, 0, 0, 3, 4, 3, 0, 0, 1];
+        write_chunk(&mut png, *b"IHDR", &header).unwrap();
+        write_chunk(&mut png, *b"PLTE", &palette).unwrap();
+        write_chunk(&mut png, *b"IDAT", &zlib::compress(&raw)).unwrap();
+        write_chunk(&mut png, *b"IEND", &[]).unwrap();
+
+        let expected: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let entry = usize::from(index(i % width, i / width));
+                palette[3 * entry..3 * entry + 3].to_vec()
+            })
+            .collect();
+        assert_pixels(&read(&png).unwrap(), width, &expected);
+    }
+}
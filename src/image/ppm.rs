@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+
+use super::{invalid_data, Image};
+use crate::color::Color;
+
+/// Writes packed 8-bit RGB data as a binary (P6) PPM.
+pub fn write(out: &mut impl Write, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    write!(out, "P6\n{width} {height}\n255\n")?;
+    out.write_all(rgb)
+}
+
+/// Reads an ASCII (P3) or binary (P6) PPM. Samples are scaled to [0, 1] but
+/// left in the file's encoding.
+pub fn read(data: &[u8]) -> io::Result<Image> {
+    let mut pos = 0;
+    let binary = match next_token(data, &mut pos) {
+        Some(b"P3") => false,
+        Some(b"P6") => true,
+        _ => return Err(invalid_data("not a P3 or P6 PPM file")),
+    };
+    let mut number = |what| {
+        next_token(data, &mut pos)
+            .and_then(|token| std::str::from_utf8(token).ok())
+            .and_then(|token| token.parse::<usize>().ok())
+            .ok_or_else(|| invalid_data(what))
+    };
+    let width = number("bad PPM width")?;
+    let height = number("bad PPM height")?;
+    let max_value = number("bad PPM maximum value")?;
+    if !(1..=65535).contains(&max_value) {
+        return Err(invalid_data("bad PPM maximum value"));
+    }
+
+    let count = width * height * 3;
+    let samples: Vec<usize> = if binary {
+        // Exactly one whitespace byte separates the header from the raster
+        let raster = data.get(pos + 1..).unwrap_or_default();
+        if max_value < 256 {
+            raster.iter().take(count).map(|&v| usize::from(v)).collect()
+        } else {
+            raster
+                .chunks_exact(2)
+                .take(count)
+                .map(|v| usize::from(u16::from_be_bytes([v[0], v[1]])))
+                .collect()
+        }
+    } else {
+        (0..count)
+            .map_while(|_| number("bad PPM sample").ok())
+            .collect()
+    };
+    if samples.len() < count {
+        return Err(invalid_data("truncated PPM data"));
+    }
+
+    let mut image = Image::new(width, height);
+    #[allow(clippy::cast_precision_loss)]
+    let scale = 1.0 / max_value as f32;
+    for (i, rgb) in samples.chunks_exact(3).enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let color = Color::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) * scale;
+        image.set(i % width, i / width, color);
+    }
+    Ok(image)
+}
+
+/// The next whitespace separated header token, skipping `#` comments.
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        match data.get(*pos)? {
+            b'#' => {
+                while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    Some(&data[start..*pos])
+}
@@ -0,0 +1,507 @@
+//! A small zlib (RFC 1950) / deflate (RFC 1951) compressor and decompressor.
+//!
+//! Matches are found with a hash-chained LZ77 search and coded with the fixed
+//! Huffman tables, which is plenty for rendered images and keeps the encoder short.
+//! The decoder handles every block type, as it has to read files from other tools.
+
+use std::io;
+
+use super::invalid_data;
+
+const WINDOW_SIZE: usize = 1 << 15;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+const HASH_BITS: u32 = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compresses `data` into a complete zlib stream.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // CMF: deflate with a 32K window, FLG: no dictionary, check bits set
+    writer.out.extend_from_slice(&[0x78, 0x01]);
+
+    // A single final block using the fixed Huffman codes
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+        if length >= MIN_MATCH {
+            writer.write_length(length);
+            writer.write_distance(distance);
+        } else {
+            writer.write_literal(data[pos]);
+        }
+        let step = length.max(1);
+        for p in pos..pos + step {
+            insert_hash(data, p, &mut head, &mut prev);
+        }
+        pos += step;
+    }
+    writer.write_literal_or_length(256);
+    writer.flush();
+
+    writer.out.extend_from_slice(&adler32(data).to_be_bytes());
+    writer.out
+}
+
+/// Decompresses a complete zlib stream and checks its Adler-32 checksum.
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let [cmf, flg, ..] = *data else {
+        return Err(invalid_data("truncated zlib stream"));
+    };
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(invalid_data("not a deflate zlib stream"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid_data("zlib preset dictionaries are not supported"));
+    }
+
+    let mut reader = BitReader::new(&data[2..]);
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => reader.stored_block(&mut out)?,
+            1 => {
+                let (literals, distances) = fixed_tables();
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            _ => return Err(invalid_data("invalid deflate block type")),
+        }
+        if last {
+            break;
+        }
+    }
+
+    let checksum = reader.aligned_bytes(4)?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&out) {
+        return Err(invalid_data("zlib checksum mismatch"));
+    }
+    Ok(out)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = usize::from(literals.decode(reader)?);
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = usize::from(LENGTH_BASE[index])
+                    + reader.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+                let index = usize::from(distances.decode(reader)?);
+                if index >= DIST_BASE.len() {
+                    return Err(invalid_data("invalid deflate distance code"));
+                }
+                let distance = usize::from(DIST_BASE[index])
+                    + reader.bits(u32::from(DIST_EXTRA[index]))? as usize;
+                if distance > out.len() {
+                    return Err(invalid_data("deflate distance before start of data"));
+                }
+                // Copy byte by byte, the match may overlap what it produces
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+            _ => return Err(invalid_data("invalid deflate length code")),
+        }
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (
+        Huffman::new(&lengths).expect("fixed literal code is complete"),
+        Huffman::new(&[5; 30]).expect("fixed distance code is complete"),
+    )
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn dynamic_tables(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    // Code lengths of the code length alphabet come in this order
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0; 19];
+    for &i in &ORDER[..code_length_count] {
+        code_lengths[i] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| invalid_data("deflate length repeat with no previous length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid_data("deflate code lengths overrun"));
+    }
+    if lengths[256] == 0 {
+        return Err(invalid_data("deflate block has no end code"));
+    }
+
+    let (literal_lengths, distance_lengths) = lengths.split_at(literal_count);
+    Ok((
+        Huffman::new(literal_lengths)?,
+        Huffman::new(distance_lengths)?,
+    ))
+}
+
+/// A canonical Huffman code, decoded one bit at a time by counting how many
+/// codes of each length come before the one being read.
+struct Huffman {
+    counts: [u16; 16], // Number of codes of each length
+    symbols: Vec<u16>, // Symbols ordered by code
+}
+
+impl Huffman {
+    #[allow(clippy::cast_possible_truncation)]
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject codes that assign more codes of some length than there is room for
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = left * 2 - i32::from(count);
+            if left < 0 {
+                return Err(invalid_data("oversubscribed deflate Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                let slot = &mut offsets[usize::from(length)];
+                symbols[usize::from(*slot)] = symbol as u16;
+                *slot += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("invalid deflate Huffman code"))
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `n` bits, least significant first.
+    #[allow(clippy::cast_possible_truncation)]
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid_data("truncated deflate stream"))?;
+            self.bits |= u32::from(*byte) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.bits & ((1u64 << n) - 1) as u32;
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte and reads whole bytes.
+    fn aligned_bytes(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        self.bits = 0;
+        self.count = 0;
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid_data("truncated deflate stream"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn stored_block(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        let header = self.aligned_bytes(4)?;
+        let length = u16::from_le_bytes([header[0], header[1]]);
+        if length != !u16::from_le_bytes([header[2], header[3]]) {
+            return Err(invalid_data("corrupt deflate stored block length"));
+        }
+        out.extend_from_slice(self.aligned_bytes(usize::from(length))?);
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn hash(data: &[u8], pos: usize) -> Option<usize> {
+    let bytes = data.get(pos..pos + MIN_MATCH)?;
+    let key = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    Some((key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize)
+}
+
+fn insert_hash(data: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if let Some(h) = hash(data, pos) {
+        prev[pos & WINDOW_MASK] = head[h];
+        head[h] = pos;
+    }
+}
+
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    let Some(h) = hash(data, pos) else {
+        return (0, 0);
+    };
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let (mut best_length, mut best_distance) = (0, 0);
+
+    let mut candidate = head[h];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+            break;
+        }
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best_length {
+            best_length = length;
+            best_distance = pos - candidate;
+            if length == max_length {
+                break;
+            }
+        }
+        let next = prev[candidate & WINDOW_MASK];
+        if next == usize::MAX || next >= candidate {
+            break;
+        }
+        candidate = next;
+    }
+    (best_length, best_distance)
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that cannot overflow before the modulo
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    b << 16 | a
+}
+
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+#[allow(clippy::cast_possible_truncation)]
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, n: u32) {
+        self.bits |= u64::from(value) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting from their most significant bit
+    fn write_code(&mut self, code: u32, n: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - n), n);
+    }
+
+    fn write_literal_or_length(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_literal(&mut self, byte: u8) {
+        self.write_literal_or_length(u32::from(byte));
+    }
+
+    fn write_length(&mut self, length: usize) {
+        let index = LENGTH_BASE.partition_point(|&base| usize::from(base) <= length) - 1;
+        self.write_literal_or_length(257 + index as u32);
+        let extra = length - usize::from(LENGTH_BASE[index]);
+        self.write_bits(extra as u32, u32::from(LENGTH_EXTRA[index]));
+    }
+
+    fn write_distance(&mut self, distance: usize) {
+        let index = DIST_BASE.partition_point(|&base| usize::from(base) <= distance) - 1;
+        self.write_code(index as u32, 5);
+        let extra = distance - usize::from(DIST_BASE[index]);
+        self.write_bits(extra as u32, u32::from(DIST_EXTRA[index]));
+    }
+
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+            self.bits = 0;
+            self.count = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::*;
+    use crate::random::Sampler;
+
+    /// A zlib stream around raw deflate blocks, with the checksum of `decoded`.
+    fn zlib_stream(blocks: &[u8], decoded: &[u8]) -> Vec<u8> {
+        let mut stream = vec![0x78, 0x01];
+        stream.extend_from_slice(blocks);
+        stream.extend_from_slice(&adler32(decoded).to_be_bytes());
+        stream
+    }
+
+    #[test]
+    fn compressed_data_decompresses_unchanged() {
+        let mut rng = Sampler::new(7);
+        let mut noise = vec![0; 5000];
+        rng.fill_bytes(&mut noise);
+        // Long runs, matches far back in the window and matches beyond it
+        let runs: Vec<u8> = (0..40_000u32).map(|i| (i / 300 % 7) as u8).collect();
+        let repeated: Vec<u8> = noise
+            .iter()
+            .copied()
+            .cycle()
+            .take(3 * WINDOW_SIZE)
+            .collect();
+        for data in [&[][..], &[42], b"abcabcabcabcabc", &noise, &runs, &repeated] {
+            let compressed = compress(data);
+            assert_eq!((compressed[2] >> 1) & 3, 1, "fixed Huffman block");
+            assert_eq!(decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn stored_blocks_are_copied() {
+        // A non-final and a final stored block: header bits, then LEN and NLEN
+        let mut blocks = vec![0b000, 3, 0, !3, !0];
+        blocks.extend_from_slice(b"raw");
+        blocks.extend_from_slice(&[0b001, 4, 0, !4, !0]);
+        blocks.extend_from_slice(b" png");
+        let stream = zlib_stream(&blocks, b"raw png");
+        assert_eq!(decompress(&stream).unwrap(), b"raw png");
+    }
+
+    #[test]
+    fn dynamic_huffman_blocks_are_decoded() {
+        // Written by zlib at level 9, which picks a dynamic block for this skewed text
+        let stream = [
+            0x78, 0xda, 0x25, 0x8a, 0xc1, 0x11, 0x00, 0x00, 0x0c, 0xc1, 0x66, 0x45, 0xf7, 0x9f,
+            0xa1, 0xd2, 0x7a, 0x90, 0xcb, 0x91, 0x93, 0xa8, 0x69, 0x59, 0x47, 0x03, 0x56, 0x9a,
+            0x69, 0x0f, 0xd2, 0x2c, 0xe6, 0x5f, 0x46, 0x2d, 0xbb, 0x35, 0x16, 0xe7,
+        ];
+        assert_eq!((stream[2] >> 1) & 3, 2, "dynamic Huffman block");
+        assert_eq!(
+            decompress(&stream).unwrap(),
+            b"abcccaaaacaabacaaaadcaabccabaabcabadaaaabbadabaababacaabaaab"
+        );
+    }
+
+    #[test]
+    fn corrupt_streams_are_rejected() {
+        let mut stream = compress(b"checksum");
+        *stream.last_mut().unwrap() ^= 1;
+        assert!(decompress(&stream).is_err());
+        assert!(decompress(&stream[..stream.len() - 6]).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Integrators turn a camera ray into the light arriving along it.
+
+use crate::background::Background;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable, HittableObject, LightList};
+use crate::interval::Interval;
+use crate::material::Scatter;
+use crate::random::{self, Sampler};
+use crate::ray::Ray;
+
+/// Every integrator with its name for `--integrator` and scene files, and a short description.
+pub const INTEGRATORS: [(&str, &str, Integrator); 3] = [
+    (
+        "path",
+        "Path tracing with light sampling, MIS and Russian roulette",
+        Integrator::Path,
+    ),
+    (
+        "bsdf",
+        "Path tracing that only finds lights by following the BSDF",
+        Integrator::Bsdf,
+    ),
+    (
+        "normals",
+        "Shading normals of the first hit as colors",
+        Integrator::Normals,
+    ),
+];
+
+pub fn find(name: &str) -> Option<Integrator> {
+    INTEGRATORS
+        .iter()
+        .find(|(integrator_name, _, _)| *integrator_name == name)
+        .map(|&(_, _, integrator)| integrator)
+}
+
+pub fn names() -> Vec<&'static str> {
+    INTEGRATORS.iter().map(|&(name, _, _)| name).collect()
+}
+
+/// Everything a path can run into.
+pub struct World<'a> {
+    pub objects: &'a HittableObject,
+    pub lights: &'a LightList,
+    pub background: &'a Background, // Color seen by rays that escape the scene
+}
+
+/// How long paths may get: at most `max_depth` hits, and past `roulette_depth`
+/// bounces Russian roulette may end them early.
+#[derive(Debug, Clone, Copy)]
+pub struct PathLength {
+    pub max_depth: u16,
+    pub roulette_depth: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Integrator {
+    #[default]
+    Path,
+    Bsdf,
+    Normals,
+}
+
+impl Integrator {
+    pub fn radiance(self, ray: Ray, world: &World, length: PathLength, rng: &mut Sampler) -> Color {
+        match self {
+            Self::Path => trace_path(ray, world, length, true, rng),
+            Self::Bsdf => trace_path(ray, world, length, false, rng),
+            Self::Normals => {
+                let mut rec = HitRecord::default();
+                if world
+                    .objects
+                    .hit(&ray, Interval::new(0.001, f32::INFINITY), &mut rec, rng)
+                {
+                    (rec.normal + Color::ONE) * 0.5
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                }
+            }
+        }
+    }
+}
+
+/// Follows one path bounce by bounce, adding up the light found along the way.
+/// With `sample_lights` every non-specular hit also sends a shadow ray to a
+/// light, and light found by either strategy is weighted by MIS.
+fn trace_path(
+    mut ray: Ray,
+    world: &World,
+    length: PathLength,
+    sample_lights: bool,
+    rng: &mut Sampler,
+) -> Color {
+    let mut radiance = Color::new(0.0, 0.0, 0.0);
+    let mut throughput = Color::ONE; // What the path so far multiplies new light by
+    let mut bsdf_pdf = None; // Density of the last bounce, `None` for camera rays and specular bounces
+
+    for bounce in 0..length.max_depth {
+        let mut rec = HitRecord::default();
+        if !world
+            .objects
+            .hit(&ray, Interval::new(0.001, f32::INFINITY), &mut rec, rng)
+        {
+            radiance += throughput * world.background.value(ray.direction());
+            break;
+        }
+
+        let mut emitted = rec.mat.emitted(&ray, &rec);
+        if let Some(bsdf_pdf) = bsdf_pdf.filter(|_| sample_lights && rec.mat.is_light()) {
+            let light_pdf = world.lights.pdf(ray.origin(), ray.direction(), ray.time());
+            emitted *= power_heuristic(bsdf_pdf, light_pdf);
+        }
+        radiance += throughput * emitted;
+
+        let srec = rec.mat.scatter(&ray, &rec, rng);
+        // Light arrives directly even when the sampled bounce is lost, e.g.
+        // reflected below a rough surface
+        let non_specular = srec
+            .as_ref()
+            .map_or_else(|| rec.mat.has_bsdf(), |srec| !srec.is_specular);
+        if sample_lights && non_specular {
+            radiance += throughput * direct_light(&ray, &rec, world, rng);
+        }
+        let Some(srec) = srec else {
+            break;
+        };
+
+        throughput *= srec.weight(&rec);
+        // Russian roulette: past `roulette_depth` bounces, end paths that carry
+        // little light with some probability and boost the survivors to match
+        if bounce >= length.roulette_depth {
+            let survival = throughput.max_element().min(0.95);
+            if random::number(rng, 0.0, 1.0) >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+
+        bsdf_pdf = (!srec.is_specular).then_some(srec.pdf);
+        ray = Ray::new(rec.p, srec.direction, ray.time());
+    }
+    radiance
+}
+
+/// Next event estimation: light reaching the hit point straight from a
+/// point sampled on one of the lights, if no surface blocks the way.
+fn direct_light(ray: &Ray, rec: &HitRecord, world: &World, rng: &mut Sampler) -> Color {
+    let black = Color::new(0.0, 0.0, 0.0);
+    let Some(sample) = world.lights.sample(rec.p, ray.time(), rng) else {
+        return black;
+    };
+    let offset = sample.point - rec.p;
+    let distance = offset.length();
+    let direction = offset / distance;
+    let bsdf = rec.mat.eval(ray, rec, direction);
+    if bsdf == black {
+        return black;
+    }
+
+    // Surfaces on the way block the light, media dim it
+    let shadow_ray = Ray::new(rec.p, direction, ray.time());
+    let transmittance =
+        world
+            .objects
+            .transmittance(&shadow_ray, Interval::new(0.001, distance * 0.999), rng);
+    if transmittance == 0.0 {
+        return black;
+    }
+    let mut light_rec = HitRecord::default();
+    if !world.objects.hit(
+        &shadow_ray,
+        Interval::new(distance * 0.999, distance * 1.001),
+        &mut light_rec,
+        rng,
+    ) {
+        return black;
+    }
+    let emitted = light_rec.mat.emitted(&shadow_ray, &light_rec);
+    let bsdf_pdf = rec.mat.pdf(ray, rec, direction);
+    let weight = power_heuristic(sample.pdf, bsdf_pdf);
+    emitted * bsdf * (transmittance * rec.foreshortening(direction) * weight / sample.pdf)
+}
+
+/// Multiple importance sampling weight for a sample drawn with density `pdf`
+/// when `other_pdf` could have drawn it as well (Veach's power heuristic, beta = 2).
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
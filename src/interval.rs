@@ -10,12 +10,15 @@ impl Interval {
     pub const fn new(min: f32, max: f32) -> Self {
         Self { min, max }
     }
-    pub fn new_with_interval(a: Self, b: Self) -> Self {
+    pub const fn new_with_interval(a: Self, b: Self) -> Self {
         Self {
             min: f32::min(a.min(), b.min()),
             max: f32::max(a.max(), b.max()),
         }
     }
+    pub fn size(self) -> f32 {
+        self.max - self.min
+    }
     pub fn contains(self, x: f32) -> bool {
         self.min <= x && x <= self.max
     }
@@ -29,14 +32,14 @@ impl Interval {
     pub const fn max(self) -> f32 {
         self.max
     }
-    pub fn set_min(&mut self, min: f32) {
+    pub const fn set_min(&mut self, min: f32) {
         self.min = min;
     }
-    pub fn set_max(&mut self, max: f32) {
+    pub const fn set_max(&mut self, max: f32) {
         self.max = max;
     }
     #[inline]
-    pub fn clamp(self, x: f32) -> f32 {
+    pub const fn clamp(self, x: f32) -> f32 {
         x.clamp(self.min, self.max)
     }
     pub fn expand(self, delta: f32) -> Self {
@@ -1,27 +1,82 @@
 use crate::color::Color;
 use crate::hittable::HitRecord;
-use crate::ray::Ray;
+use crate::random::Sampler;
+use crate::ray::{Direction, Ray};
 use crate::texture::Texture;
 
+mod conductor;
 mod dielectric;
+mod diffuse_light;
 mod lambertian;
 mod metal;
+mod microfacet;
+mod phase_function;
+mod rough_dielectric;
+mod volume;
 
+pub use conductor::ComplexIor;
+pub use phase_function::PhaseFunction;
+
+/// A direction picked by a material, with what it takes to weight it.
+#[derive(Debug, Clone)]
+pub struct ScatterRecord {
+    pub direction: Direction, // Unit direction the light continues in
+    pub bsdf: Color,          // BSDF value for `direction`, the whole attenuation when specular
+    pub pdf: f32,             // Solid angle density of `direction`, unused when specular
+    pub is_specular: bool, // A delta lobe: `eval` and `pdf` cannot see it, lights are not sampled
+}
+
+impl ScatterRecord {
+    pub const fn specular(direction: Direction, attenuation: Color) -> Self {
+        Self {
+            direction,
+            bsdf: attenuation,
+            pdf: 1.0,
+            is_specular: true,
+        }
+    }
+
+    /// BSDF times cosine over density, the factor the light coming back along
+    /// `direction` is multiplied by.
+    pub fn weight(&self, rec: &HitRecord) -> Color {
+        if self.is_specular {
+            self.bsdf
+        } else {
+            self.bsdf * (rec.foreshortening(self.direction) / self.pdf)
+        }
+    }
+}
+
+/// `r_in` is the ray that found the hit, light leaves along its reverse and
+/// arrives from `direction`.
 pub trait Scatter {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord>;
+
+    /// BSDF value for light arriving from `direction`, black for specular materials.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Direction) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Density with which `scatter` picks `direction`, zero for specular materials.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Direction) -> f32 {
+        0.0
+    }
+
+    /// Light given off by the surface at the hit point, black for anything that is not a light.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Material {
     Lambertian(lambertian::Lambertian),
     Metal(metal::Metal),
+    Conductor(conductor::Conductor),
     Dielectric(dielectric::Dielectric),
+    RoughDielectric(rough_dielectric::RoughDielectric),
+    DiffuseLight(diffuse_light::DiffuseLight),
+    Volume(volume::Volume),
 }
 
 impl Default for Material {
@@ -40,23 +95,164 @@ impl Material {
     pub fn metal(a: Color, f: f32) -> Self {
         Self::Metal(metal::Metal::new(&a, f))
     }
+    /// A GGX microfacet metal, e.g. `Material::conductor(ComplexIor::GOLD, 0.3, 0.3)`.
+    /// Roughness 0 is a perfect mirror, different roughness along and across
+    /// the world x axis projected onto the surface looks like brushed metal.
+    pub fn conductor(ior: ComplexIor, roughness_u: f32, roughness_v: f32) -> Self {
+        Self::Conductor(conductor::Conductor::new(ior, roughness_u, roughness_v))
+    }
     pub const fn dielectric(index_of_refraction: f32) -> Self {
         Self::Dielectric(dielectric::Dielectric::new(index_of_refraction))
     }
+    /// Frosted glass, rougher where the red channel of `roughness` is higher.
+    pub const fn rough_dielectric(index_of_refraction: f32, roughness: Texture) -> Self {
+        Self::RoughDielectric(rough_dielectric::RoughDielectric::new(
+            index_of_refraction,
+            roughness,
+        ))
+    }
+    pub const fn diffuse_light(emit: Color) -> Self {
+        Self::DiffuseLight(diffuse_light::DiffuseLight::new(emit))
+    }
+    pub const fn diffuse_light_with_texture(emit: Texture, one_sided: bool) -> Self {
+        Self::DiffuseLight(diffuse_light::DiffuseLight::new_with_texture(
+            emit, one_sided,
+        ))
+    }
+    /// Scattering inside a medium, paired with [`HittableObject::constant_medium`]
+    /// and the other media.
+    ///
+    /// [`HittableObject::constant_medium`]: crate::hittable::HittableObject::constant_medium
+    pub const fn volume(albedo: Texture, phase_function: PhaseFunction) -> Self {
+        Self::Volume(volume::Volume::new(albedo, phase_function))
+    }
+    pub const fn isotropic(albedo: Texture) -> Self {
+        Self::volume(albedo, PhaseFunction::Isotropic)
+    }
+    pub const fn is_light(&self) -> bool {
+        matches!(self, Self::DiffuseLight(_))
+    }
+    /// Lets the scattering material of a medium glow with `emission` where it
+    /// absorbs, other materials are returned unchanged.
+    #[must_use]
+    pub fn with_emission(self, emission: Color) -> Self {
+        match self {
+            Self::Volume(volume) => Self::Volume(volume.with_emission(emission)),
+            other => other,
+        }
+    }
+    /// `eval` and `pdf` describe how this material scatters, so light sampling
+    /// can find light for it. Not for lights and perfectly specular materials.
+    pub const fn has_bsdf(&self) -> bool {
+        matches!(
+            self,
+            Self::Lambertian(_) | Self::Conductor(_) | Self::RoughDielectric(_) | Self::Volume(_)
+        )
+    }
+    /// Hits with this material are points inside a medium rather than on a surface.
+    pub const fn is_volumetric(&self) -> bool {
+        matches!(self, Self::Volume(_))
+    }
 }
 
 impl Scatter for Material {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        match self {
+            Self::Lambertian(lamb) => lamb.scatter(r_in, rec, rng),
+            Self::Metal(met) => met.scatter(r_in, rec, rng),
+            Self::Conductor(conductor) => conductor.scatter(r_in, rec, rng),
+            Self::Dielectric(die) => die.scatter(r_in, rec, rng),
+            Self::RoughDielectric(glass) => glass.scatter(r_in, rec, rng),
+            Self::DiffuseLight(light) => light.scatter(r_in, rec, rng),
+            Self::Volume(volume) => volume.scatter(r_in, rec, rng),
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> Color {
+        match self {
+            Self::Lambertian(lamb) => lamb.eval(r_in, rec, direction),
+            Self::Conductor(conductor) => conductor.eval(r_in, rec, direction),
+            Self::RoughDielectric(glass) => glass.eval(r_in, rec, direction),
+            Self::Volume(volume) => volume.eval(r_in, rec, direction),
+            Self::Metal(_) | Self::Dielectric(_) | Self::DiffuseLight(_) => {
+                Color::new(0.0, 0.0, 0.0)
+            }
+        }
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> f32 {
         match self {
-            Self::Lambertian(lamb) => lamb.scatter(r_in, rec, attenuation, scattered),
-            Self::Metal(met) => met.scatter(r_in, rec, attenuation, scattered),
-            Self::Dielectric(die) => die.scatter(r_in, rec, attenuation, scattered),
+            Self::Lambertian(lamb) => lamb.pdf(r_in, rec, direction),
+            Self::Conductor(conductor) => conductor.pdf(r_in, rec, direction),
+            Self::RoughDielectric(glass) => glass.pdf(r_in, rec, direction),
+            Self::Volume(volume) => volume.pdf(r_in, rec, direction),
+            Self::Metal(_) | Self::Dielectric(_) | Self::DiffuseLight(_) => 0.0,
         }
     }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        match self {
+            Self::DiffuseLight(light) => light.emitted(r_in, rec),
+            Self::Volume(volume) => volume.emitted(r_in, rec),
+            Self::Lambertian(_)
+            | Self::Metal(_)
+            | Self::Conductor(_)
+            | Self::Dielectric(_)
+            | Self::RoughDielectric(_) => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+
+    /// Checks that `scatter` agrees with `eval` and `pdf` for light leaving
+    /// along the reverse of `r_in`: every sampled density matches `pdf`, and
+    /// the mean sampled weight matches the BSDF integrated with directions
+    /// from `reference`, within `tolerance`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn assert_sampled_weights_match_eval(
+        material: &impl Scatter,
+        r_in: &Ray,
+        rec: &HitRecord,
+        reference: fn(&mut Sampler) -> (Direction, f32),
+        tolerance: f32,
+        rng: &mut Sampler,
+    ) {
+        const COUNT: u32 = 400_000;
+        // Summed in double precision, single precision loses the small terms
+        let mean = |f: &mut dyn FnMut() -> Color| {
+            let sum: DVec3 = (0..COUNT).map(|_| f().as_dvec3()).sum();
+            (sum / f64::from(COUNT)).as_vec3()
+        };
+
+        // Light scattered, estimated by sampling the BSDF ...
+        let sampled = mean(&mut || {
+            material
+                .scatter(r_in, rec, rng)
+                .map_or(Color::ZERO, |srec| {
+                    let pdf = material.pdf(r_in, rec, srec.direction);
+                    assert!(
+                        (pdf - srec.pdf).abs() <= 1e-3 * pdf,
+                        "pdf {pdf} vs {}",
+                        srec.pdf
+                    );
+                    srec.weight(rec)
+                })
+        });
+
+        // ... and by evaluating it for directions from `reference`
+        let evaluated = mean(&mut || {
+            let (direction, pdf) = reference(rng);
+            material.eval(r_in, rec, direction) * (rec.foreshortening(direction) / pdf)
+        });
+        assert!(
+            sampled.abs_diff_eq(evaluated, tolerance) && sampled.max_element() <= 1.0,
+            "arriving along {}: sampled {sampled}, evaluated {evaluated}",
+            r_in.direction()
+        );
+    }
 }
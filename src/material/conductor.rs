@@ -0,0 +1,213 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::microfacet::TrowbridgeReitz;
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::Sampler;
+use crate::ray::{Direction, Ray};
+use crate::utility::{self, Onb};
+
+/// The complex index of refraction of a metal per RGB channel: `eta` bends
+/// the light, `k` absorbs it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexIor {
+    pub eta: Color,
+    pub k: Color,
+}
+
+impl ComplexIor {
+    pub const GOLD: Self = Self::new(
+        Color::new(0.143, 0.374, 1.442),
+        Color::new(3.983, 2.385, 1.603),
+    );
+    pub const COPPER: Self = Self::new(
+        Color::new(0.200, 0.924, 1.102),
+        Color::new(3.912, 2.452, 2.142),
+    );
+    pub const SILVER: Self = Self::new(
+        Color::new(0.155, 0.117, 0.138),
+        Color::new(4.828, 3.122, 2.147),
+    );
+    pub const ALUMINUM: Self = Self::new(
+        Color::new(1.657, 0.880, 0.521),
+        Color::new(9.224, 6.270, 4.837),
+    );
+
+    /// Every preset with its name for scene files.
+    pub const PRESETS: [(&str, Self); 4] = [
+        ("gold", Self::GOLD),
+        ("copper", Self::COPPER),
+        ("silver", Self::SILVER),
+        ("aluminum", Self::ALUMINUM),
+    ];
+
+    pub const fn new(eta: Color, k: Color) -> Self {
+        Self { eta, k }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset_name, _)| *preset_name == name)
+            .map(|&(_, ior)| ior)
+    }
+
+    /// Fraction of the light arriving at `cos_theta` to the normal that the
+    /// metal reflects, from the full Fresnel equations for conductors.
+    pub fn reflectance(self, cos_theta: f32) -> Color {
+        let cos2 = Color::splat(cos_theta.clamp(0.0, 1.0).powi(2));
+        let sin2 = Color::ONE - cos2;
+        let eta2 = self.eta * self.eta;
+        let k2 = self.k * self.k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).powf(0.5);
+        let a = (0.5 * (a2_plus_b2 + t0)).max(Color::ZERO).powf(0.5);
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos_theta * a;
+        let s_polarized = (t1 - t2) / (t1 + t2);
+
+        let sin4 = sin2 * sin2;
+        let t3 = cos2 * a2_plus_b2 + sin4;
+        let t4 = t2 * sin2;
+        let p_polarized = s_polarized * (t3 - t4) / (t3 + t4);
+        0.5 * (s_polarized + p_polarized)
+    }
+}
+
+/// A rough metal: microfacets from the GGX distribution, each a perfect
+/// mirror with the metal's Fresnel reflectance.
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    ior: ComplexIor,
+    distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    /// Roughness runs along the world x axis projected onto the surface, and
+    /// across it.
+    pub fn new(ior: ComplexIor, roughness_u: f32, roughness_v: f32) -> Self {
+        Self {
+            ior,
+            distribution: TrowbridgeReitz::new(roughness_u, roughness_v),
+        }
+    }
+
+    fn frame(rec: &HitRecord) -> Onb {
+        Onb::with_tangent(rec.normal, Direction::X)
+    }
+
+    /// The microfacet BRDF for the local directions `wo` and `wi`, with the
+    /// density the sampling picks `wi` with.
+    fn eval_local(&self, wo: Direction, wi: Direction) -> (Color, f32) {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return (Color::ZERO, 0.0);
+        }
+        let m = (wo + wi).normalize();
+        let d = self.distribution.d(m);
+        let fresnel = self.ior.reflectance(wo.dot(m));
+        let bsdf = fresnel * (d * self.distribution.g(wo, wi) / (4.0 * wo.z * wi.z));
+        // Visible normal density, carried over from m to the reflected direction
+        let pdf = self.distribution.visible_d(wo, m) / (4.0 * wo.dot(m));
+        (bsdf, pdf)
+    }
+}
+
+impl Scatter for Conductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        let unit_direction = r_in.direction().normalize();
+        if self.distribution.is_smooth() {
+            let cos_theta = -unit_direction.dot(rec.normal);
+            let direction = utility::reflect(unit_direction, rec.normal);
+            return Some(ScatterRecord::specular(
+                direction,
+                self.ior.reflectance(cos_theta),
+            ));
+        }
+
+        let frame = Self::frame(rec);
+        let wo = frame.to_local(-unit_direction);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let m = self.distribution.sample_visible_normal(wo, rng);
+        let wi = utility::reflect(-wo, m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let (bsdf, pdf) = self.eval_local(wo, wi);
+        Some(ScatterRecord {
+            direction: frame.to_world(wi),
+            bsdf,
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::ZERO;
+        }
+        let frame = Self::frame(rec);
+        let wo = frame.to_local(-r_in.direction().normalize());
+        self.eval_local(wo, frame.to_local(direction)).0
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> f32 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+        let frame = Self::frame(rec);
+        let wo = frame.to_local(-r_in.direction().normalize());
+        self.eval_local(wo, frame.to_local(direction)).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_sampled_weights_match_eval;
+    use crate::random;
+    use crate::ray::Point;
+
+    #[test]
+    fn head_on_reflectance_matches_the_closed_form() {
+        for (name, ior) in ComplexIor::PRESETS {
+            let (eta, k) = (ior.eta, ior.k);
+            let k2 = k * k;
+            let expected =
+                ((eta - Color::ONE).powf(2.0) + k2) / ((eta + Color::ONE).powf(2.0) + k2);
+            let reflectance = ior.reflectance(1.0);
+            assert!(
+                reflectance.abs_diff_eq(expected, 1e-4),
+                "{name}: {reflectance}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn sampled_weights_match_the_evaluated_bsdf() {
+        let rec = HitRecord {
+            p: Point::ZERO,
+            normal: Direction::Z,
+            front_face: true,
+            ..HitRecord::default()
+        };
+        let r_in = Ray::new(
+            Point::new(-0.5, 0.2, 1.0),
+            Direction::new(0.5, -0.2, -1.0),
+            0.0,
+        );
+        for (u, v) in [(0.4, 0.4), (0.8, 0.8), (0.3, 0.7)] {
+            let material = Conductor::new(ComplexIor::GOLD, u, v);
+            let mut rng = Sampler::new(37);
+            assert_sampled_weights_match_eval(
+                &material,
+                &r_in,
+                &rec,
+                random::uniform_hemisphere,
+                0.01,
+                &mut rng,
+            );
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::color::Color;
 use crate::hittable::HitRecord;
-use crate::material::Scatter;
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::{self, Sampler};
 use crate::ray::Ray;
-use crate::{random, utility};
+use crate::utility;
 
 #[derive(Debug, Clone)]
 pub struct Dielectric {
@@ -24,14 +25,7 @@ impl Dielectric {
 }
 
 impl Scatter for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
         let refraction_ratio: f32 = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -45,15 +39,16 @@ impl Scatter for Dielectric {
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
         let direction = if cannot_refract
-            || Self::reflectance(cos_theta, refraction_ratio) > random::number(0.0, 1.0)
+            || Self::reflectance(cos_theta, refraction_ratio) > random::number(rng, 0.0, 1.0)
         {
             utility::reflect(unit_direction, rec.normal)
         } else {
             utility::refract(unit_direction, rec.normal, refraction_ratio)
         };
 
-        *scattered = Ray::new(rec.p, direction, r_in.time());
-
-        true
+        Some(ScatterRecord::specular(
+            direction.normalize(),
+            Color::new(1.0, 1.0, 1.0),
+        ))
     }
 }
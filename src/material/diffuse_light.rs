@@ -0,0 +1,34 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::Sampler;
+use crate::ray::Ray;
+use crate::texture::Texture;
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffuseLight {
+    emit: Texture,
+    one_sided: bool, // Only emit from the side the normal points to
+}
+
+impl DiffuseLight {
+    pub const fn new(c: Color) -> Self {
+        Self::new_with_texture(Texture::solid_color(c), false)
+    }
+    pub const fn new_with_texture(emit: Texture, one_sided: bool) -> Self {
+        Self { emit, one_sided }
+    }
+}
+
+impl Scatter for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut Sampler) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if self.one_sided && !rec.front_face {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.emit.value(rec.u, rec.v, &rec.p)
+    }
+}
@@ -1,9 +1,12 @@
+use std::f32::consts::PI;
+
 use crate::color::Color;
 use crate::hittable::HitRecord;
-use crate::material::Scatter;
-use crate::random;
-use crate::ray::{Point, Ray};
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Ray};
 use crate::texture::Texture;
+use crate::utility::Onb;
 
 #[derive(Debug, Clone, Default)]
 pub struct Lambertian {
@@ -20,22 +23,26 @@ impl Lambertian {
 }
 
 impl Scatter for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + random::unit_vector();
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        let (local, pdf) = random::cosine_hemisphere(rng);
+        let direction = Onb::new(rec.normal).to_world(local);
+        Some(ScatterRecord {
+            direction,
+            bsdf: self.eval(r_in, rec, direction),
+            pdf,
+            is_specular: false,
+        })
+    }
 
-        // Catch degenerate scatter direction
-        if scatter_direction.abs_diff_eq(Point::new(0.0, 0.0, 0.0), 1e-8) {
-            scatter_direction = rec.normal;
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: Direction) -> Color {
+        if rec.normal.dot(direction) > 0.0 {
+            self.albedo.value(rec.u, rec.v, &rec.p) / PI
+        } else {
+            Color::new(0.0, 0.0, 0.0)
         }
+    }
 
-        *scattered = Ray::new(rec.p, scatter_direction, r_in.time());
-        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
-        true
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Direction) -> f32 {
+        rec.normal.dot(direction).max(0.0) / PI
     }
 }
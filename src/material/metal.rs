@@ -1,8 +1,9 @@
 use crate::color::Color;
 use crate::hittable::HitRecord;
-use crate::material::Scatter;
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::{self, Sampler};
 use crate::ray::Ray;
-use crate::{random, utility};
+use crate::utility;
 
 #[derive(Debug, Clone, Default)]
 pub struct Metal {
@@ -20,20 +21,10 @@ impl Metal {
 }
 
 impl Scatter for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        // Fuzzed reflections are still traced as a single specular lobe
         let reflected = utility::reflect(r_in.direction().normalize(), rec.normal);
-        *scattered = Ray::new(
-            rec.p,
-            reflected + random::unit_vector() * self.fuzz,
-            r_in.time(),
-        );
-        *attenuation = self.albedo;
-        scattered.direction().dot(rec.normal) > 0.0
+        let direction = (reflected + random::uniform_sphere(rng).0 * self.fuzz).normalize();
+        (direction.dot(rec.normal) > 0.0).then(|| ScatterRecord::specular(direction, self.albedo))
     }
 }
@@ -0,0 +1,183 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::random::{self, Sampler};
+use crate::ray::Direction;
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, with Smith
+/// masking-shadowing. Directions are in the local frame of the surface, with
+/// the macro normal along +z and the x axis along the first roughness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha_x: f32,
+    alpha_y: f32,
+}
+
+impl TrowbridgeReitz {
+    /// Takes perceptual roughness in [0, 1] along x and y, squared into the
+    /// distribution's alphas. Those stay above zero, so one smooth axis of an
+    /// otherwise rough surface does not divide by zero.
+    pub fn new(roughness_x: f32, roughness_y: f32) -> Self {
+        let alpha = |roughness: f32| roughness.clamp(0.0, 1.0).powi(2).max(1e-4);
+        Self {
+            alpha_x: alpha(roughness_x),
+            alpha_y: alpha(roughness_y),
+        }
+    }
+
+    /// So smooth that it is better treated as a perfect mirror.
+    pub fn is_smooth(self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacet normals `m` per unit solid angle, projected onto the macro surface.
+    pub fn d(self, m: Direction) -> f32 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let stretched =
+            Direction::new(m.x / self.alpha_x, m.y / self.alpha_y, m.z).length_squared();
+        1.0 / (PI * self.alpha_x * self.alpha_y * stretched * stretched)
+    }
+
+    /// Smith's auxiliary function: the shadowed area of the microsurface
+    /// seen from `w`, relative to its projected area.
+    fn lambda(self, w: Direction) -> f32 {
+        if w.z == 0.0 {
+            return f32::INFINITY;
+        }
+        let projected =
+            Direction::new(self.alpha_x * w.x, self.alpha_y * w.y, 0.0).length_squared();
+        ((1.0 + projected / (w.z * w.z)).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of the microfacets facing `w` that are visible from it.
+    pub fn g1(self, w: Direction) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of the microfacets visible from both `wo` and `wi`, height correlated.
+    pub fn g(self, wo: Direction, wi: Direction) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals seen from `wo`, per unit solid angle of `m`.
+    pub fn visible_d(self, wo: Direction, m: Direction) -> f32 {
+        self.g1(wo) * wo.dot(m).max(0.0) * self.d(m) / wo.z
+    }
+
+    /// Picks a microfacet normal visible from `wo` with density
+    /// [`Self::visible_d`], by sampling the projection of a hemisphere
+    /// stretched by the roughness (Heitz 2018).
+    pub fn sample_visible_normal(self, wo: Direction, rng: &mut Sampler) -> Direction {
+        let stretched = Direction::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalize();
+        let along = if stretched.z < 0.9999 {
+            Direction::Z.cross(stretched).normalize()
+        } else {
+            Direction::X
+        };
+        let across = stretched.cross(along);
+
+        // A point on the unit disk, squeezed onto the part of the disk the
+        // hemisphere's projection covers
+        let radius = random::number(rng, 0.0f32, 1.0).sqrt();
+        let phi = random::number(rng, 0.0, TAU);
+        let x = radius * phi.cos();
+        let y = radius * phi.sin();
+        let s = 0.5 * (1.0 + stretched.z);
+        let y = (1.0 - s).mul_add(x.mul_add(-x, 1.0).max(0.0).sqrt(), s * y);
+        let height = (1.0 - x * x - y * y).max(0.0).sqrt();
+        let normal = along * x + across * y + stretched * height;
+
+        Direction::new(
+            self.alpha_x * normal.x,
+            self.alpha_y * normal.y,
+            normal.z.max(1e-6),
+        )
+        .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 200_000;
+
+    fn distributions() -> [TrowbridgeReitz; 3] {
+        [
+            TrowbridgeReitz::new(0.3, 0.3),
+            TrowbridgeReitz::new(0.8, 0.8),
+            TrowbridgeReitz::new(0.3, 0.7),
+        ]
+    }
+
+    /// Averages `f(m) / pdf` over directions spread evenly over the upper hemisphere.
+    #[allow(clippy::cast_precision_loss)]
+    fn hemisphere_integral(f: impl Fn(Direction) -> f32) -> f32 {
+        let mut rng = Sampler::new(29);
+        let sum: f32 = (0..SAMPLES)
+            .map(|_| {
+                let (m, pdf) = random::uniform_hemisphere(&mut rng);
+                f(m) / pdf
+            })
+            .sum();
+        sum / SAMPLES as f32
+    }
+
+    #[test]
+    fn projected_normals_cover_the_surface_once() {
+        for distribution in distributions() {
+            let area = hemisphere_integral(|m| distribution.d(m) * m.z);
+            assert!((area - 1.0).abs() < 0.02, "{distribution:?} covers {area}");
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn visible_normals_follow_their_density() {
+        let wo = Direction::new(0.6, -0.3, 0.5).normalize();
+        for distribution in distributions() {
+            let total = hemisphere_integral(|m| distribution.visible_d(wo, m));
+            assert!(
+                (total - 1.0).abs() < 0.02,
+                "{distribution:?} totals {total}"
+            );
+
+            // The mean normal from sampling matches the mean under the density
+            let expected = Direction::new(
+                hemisphere_integral(|m| m.x * distribution.visible_d(wo, m)),
+                hemisphere_integral(|m| m.y * distribution.visible_d(wo, m)),
+                hemisphere_integral(|m| m.z * distribution.visible_d(wo, m)),
+            );
+            let mut rng = Sampler::new(31);
+            let mean = (0..SAMPLES)
+                .map(|_| distribution.sample_visible_normal(wo, &mut rng))
+                .sum::<Direction>()
+                / SAMPLES as f32;
+            assert!(
+                mean.abs_diff_eq(expected, 0.01),
+                "{distribution:?}: sampled {mean}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_smooth_axis_keeps_densities_finite() {
+        let distribution = TrowbridgeReitz::new(0.0, 0.5);
+        assert!(!distribution.is_smooth());
+        let mut rng = Sampler::new(67);
+        for _ in 0..10_000 {
+            let (wo, _) = random::uniform_hemisphere(&mut rng);
+            let m = distribution.sample_visible_normal(wo, &mut rng);
+            let values = [
+                distribution.d(m),
+                distribution.g(wo, m),
+                distribution.visible_d(wo, m),
+            ];
+            assert!(
+                m.is_finite() && values.iter().all(|v| v.is_finite() && *v >= 0.0),
+                "wo {wo}, m {m}: {values:?}"
+            );
+        }
+    }
+}
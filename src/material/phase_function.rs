@@ -0,0 +1,36 @@
+use std::f32::consts::PI;
+
+use crate::random::{self, Sampler};
+use crate::ray::Direction;
+use crate::utility::Onb;
+
+/// How a medium spreads the light it scatters over directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseFunction {
+    Isotropic,             // Evenly in every direction
+    HenyeyGreenstein(f32), // Asymmetry g in (-1, 1), forward for g > 0 and backward for g < 0
+}
+
+impl PhaseFunction {
+    /// Density of light travelling along `incoming` continuing along
+    /// `outgoing`, both unit vectors. Sampling follows it exactly, so it is
+    /// also the density of [`Self::sample`].
+    pub fn eval(self, incoming: Direction, outgoing: Direction) -> f32 {
+        match self {
+            Self::Isotropic => 1.0 / (4.0 * PI),
+            Self::HenyeyGreenstein(g) => random::henyey_greenstein_pdf(incoming.dot(outgoing), g),
+        }
+    }
+
+    /// Picks the direction light travelling along the unit vector `incoming`
+    /// continues in, and its density per unit solid angle.
+    pub fn sample(self, incoming: Direction, rng: &mut Sampler) -> (Direction, f32) {
+        match self {
+            Self::Isotropic => random::uniform_sphere(rng),
+            Self::HenyeyGreenstein(g) => {
+                let (local, pdf) = random::henyey_greenstein(rng, g);
+                (Onb::new(incoming).to_world(local), pdf)
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::dielectric::Dielectric;
+use crate::material::microfacet::TrowbridgeReitz;
+use crate::material::{Scatter, ScatterRecord};
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Ray};
+use crate::texture::Texture;
+use crate::utility::{self, Onb};
+
+/// Frosted glass: GGX microfacets that each reflect or refract like smooth
+/// glass (Walter et al. 2007). Where the roughness is 0 it is a [`Dielectric`].
+#[derive(Debug, Clone)]
+pub struct RoughDielectric {
+    ir: f32,            // Index of refraction
+    roughness: Texture, // The red channel, clamped to [0, 1]
+}
+
+impl RoughDielectric {
+    pub const fn new(index_of_refraction: f32, roughness: Texture) -> Self {
+        Self {
+            ir: index_of_refraction,
+            roughness,
+        }
+    }
+
+    fn distribution(&self, rec: &HitRecord) -> TrowbridgeReitz {
+        let roughness = self.roughness.value(rec.u, rec.v, &rec.p).x;
+        TrowbridgeReitz::new(roughness, roughness)
+    }
+
+    /// Index of refraction behind the surface over the one in front of it.
+    fn eta(&self, rec: &HitRecord) -> f32 {
+        if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        }
+    }
+
+    /// Local frame with +z along the normal, on the side the ray came from.
+    fn frame(r_in: &Ray, rec: &HitRecord) -> (Onb, Direction) {
+        let frame = Onb::new(rec.normal);
+        let wo = frame.to_local(-r_in.direction().normalize());
+        (frame, wo)
+    }
+
+    /// The BSDF for the local directions `wo` and `wi`, with the density the
+    /// sampling picks `wi` with. Light arriving from below the surface is
+    /// refracted, from above reflected.
+    fn eval_local(
+        distribution: TrowbridgeReitz,
+        eta: f32,
+        wo: Direction,
+        wi: Direction,
+    ) -> (f32, f32) {
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return (0.0, 0.0);
+        }
+        let reflected = wi.z > 0.0;
+        // The microfacet normal that turns wo into wi, on wo's side
+        let m = if reflected { wo + wi } else { wo + wi * eta }.normalize_or_zero();
+        let m = if m.z < 0.0 { -m } else { m };
+        if m == Direction::ZERO || wo.dot(m) <= 0.0 || wi.dot(m) * wi.z <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let reflectance = fresnel(wo.dot(m), eta);
+        let d = distribution.d(m);
+        let g = distribution.g(wo, wi);
+        let visible = distribution.visible_d(wo, m);
+        if reflected {
+            let bsdf = reflectance * d * g / (4.0 * wo.z * wi.z);
+            let pdf = reflectance * visible / (4.0 * wo.dot(m));
+            (bsdf, pdf)
+        } else {
+            // Like `Dielectric`, without scaling radiance by 1 / eta^2 on the
+            // way in, which cancels once the light leaves the glass again
+            let transmittance = 1.0 - reflectance;
+            let denominator = wi.dot(m).mul_add(eta, wo.dot(m)).powi(2);
+            let jacobian = eta * eta * wi.dot(m).abs() / denominator;
+            let bsdf = transmittance * d * g * wo.dot(m) * jacobian / (wo.z * wi.z.abs());
+            let pdf = transmittance * visible * jacobian;
+            (bsdf, pdf)
+        }
+    }
+}
+
+/// Fraction of the light reflected by smooth glass with relative index of
+/// refraction `eta`, arriving at `cos_theta` to the normal. 1 under total
+/// internal reflection.
+fn fresnel(cos_theta: f32, eta: f32) -> f32 {
+    let sin2_t = cos_theta.mul_add(-cos_theta, 1.0).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = eta.mul_add(cos_theta, -cos_t) / eta.mul_add(cos_theta, cos_t);
+    let perpendicular = eta.mul_add(-cos_t, cos_theta) / eta.mul_add(cos_t, cos_theta);
+    0.5 * parallel.mul_add(parallel, perpendicular * perpendicular)
+}
+
+impl Scatter for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        let distribution = self.distribution(rec);
+        if distribution.is_smooth() {
+            return Dielectric::new(self.ir).scatter(r_in, rec, rng);
+        }
+
+        let (frame, wo) = Self::frame(r_in, rec);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(rec);
+        let m = distribution.sample_visible_normal(wo, rng);
+        // Total internal reflection has a reflectance of 1, so it always reflects
+        let wi = if random::number(rng, 0.0, 1.0) < fresnel(wo.dot(m), eta) {
+            Some(utility::reflect(-wo, m)).filter(|wi| wi.z > 0.0)
+        } else {
+            Some(utility::refract(-wo, m, 1.0 / eta)).filter(|wi| wi.z < 0.0)
+        }?;
+        let (bsdf, pdf) = Self::eval_local(distribution, eta, wo, wi);
+        (pdf > 0.0).then(|| ScatterRecord {
+            direction: frame.to_world(wi).normalize(),
+            bsdf: Color::splat(bsdf),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> Color {
+        let distribution = self.distribution(rec);
+        if distribution.is_smooth() {
+            return Color::ZERO;
+        }
+        let (frame, wo) = Self::frame(r_in, rec);
+        let wi = frame.to_local(direction);
+        Color::splat(Self::eval_local(distribution, self.eta(rec), wo, wi).0)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> f32 {
+        let distribution = self.distribution(rec);
+        if distribution.is_smooth() {
+            return 0.0;
+        }
+        let (frame, wo) = Self::frame(r_in, rec);
+        let wi = frame.to_local(direction);
+        Self::eval_local(distribution, self.eta(rec), wo, wi).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_sampled_weights_match_eval;
+    use crate::ray::Point;
+
+    fn hit(front_face: bool) -> HitRecord {
+        HitRecord {
+            p: Point::ZERO,
+            normal: Direction::Z,
+            front_face,
+            ..HitRecord::default()
+        }
+    }
+
+    /// A ray arriving at the origin from above, `degrees` away from the normal.
+    fn incoming(degrees: f32) -> Ray {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Ray::new(
+            Point::new(-sin, 0.0, cos),
+            Direction::new(sin, 0.0, -cos),
+            0.0,
+        )
+    }
+
+    #[test]
+    fn sampled_weights_match_the_evaluated_bsdf() {
+        for (front_face, degrees, roughness) in
+            [(true, 30.0, 0.7), (true, 70.0, 0.6), (false, 20.0, 0.6)]
+        {
+            let glass = RoughDielectric::new(1.5, Texture::solid_color(Color::splat(roughness)));
+            let mut rng = Sampler::new(41);
+            assert_sampled_weights_match_eval(
+                &glass,
+                &incoming(degrees),
+                &hit(front_face),
+                random::uniform_sphere,
+                0.02,
+                &mut rng,
+            );
+        }
+    }
+
+    #[test]
+    fn light_beyond_the_critical_angle_stays_inside() {
+        // From inside glass of index 1.5 the critical angle is about 42 degrees
+        let glass = RoughDielectric::new(1.5, Texture::solid_color(Color::splat(0.1)));
+        let (rec, r_in) = (hit(false), incoming(70.0));
+        let mut rng = Sampler::new(43);
+        let count = 50_000u16;
+        let reflected = (0..count)
+            .filter_map(|_| glass.scatter(&r_in, &rec, &mut rng))
+            .map(|srec| srec.weight(&rec).x * f32::from(srec.direction.z > 0.0))
+            .sum::<f32>()
+            / f32::from(count);
+        assert!(reflected > 0.98, "{reflected} reflected");
+    }
+}
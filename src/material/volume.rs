@@ -0,0 +1,58 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::{PhaseFunction, Scatter, ScatterRecord};
+use crate::random::Sampler;
+use crate::ray::{Direction, Ray};
+use crate::texture::Texture;
+
+/// Scattering at points inside a participating medium: the albedo is the
+/// part of the light that scatters rather than being absorbed, the phase
+/// function where it goes.
+#[derive(Debug, Clone)]
+pub struct Volume {
+    albedo: Texture,
+    phase_function: PhaseFunction,
+    emission: Color, // Radiance given off where the medium absorbs, set per hit
+}
+
+impl Volume {
+    pub const fn new(albedo: Texture, phase_function: PhaseFunction) -> Self {
+        Self {
+            albedo,
+            phase_function,
+            emission: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+    pub fn with_emission(self, emission: Color) -> Self {
+        Self { emission, ..self }
+    }
+}
+
+impl Scatter for Volume {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Sampler) -> Option<ScatterRecord> {
+        let (direction, pdf) = self
+            .phase_function
+            .sample(r_in.direction().normalize(), rng);
+        Some(ScatterRecord {
+            direction,
+            bsdf: self.eval(r_in, rec, direction),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Direction) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p) * self.pdf(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, _rec: &HitRecord, direction: Direction) -> f32 {
+        self.phase_function
+            .eval(r_in.direction().normalize(), direction)
+    }
+
+    /// Only the part of the medium that absorbs glows, the rest scatters.
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        let absorbed = Color::ONE - self.albedo.value(rec.u, rec.v, &rec.p);
+        self.emission * absorbed.max(Color::new(0.0, 0.0, 0.0))
+    }
+}
@@ -0,0 +1,379 @@
+//! Wavefront OBJ meshes and their MTL material libraries.
+//!
+//! Supported statements are `v`, `vt`, `vn` and `f` (with negative, relative
+//! indices and polygons split into triangle fans), `o` and `g` groups, and
+//! `mtllib`/`usemtl`. Other statements such as `s` or `l` are ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glam::Vec2;
+
+use crate::color::Color;
+use crate::hittable::MeshData;
+use crate::material::Material;
+use crate::ray::{Direction, Point};
+
+mod mtl;
+
+#[derive(Debug)]
+pub struct ObjError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.path.display(), self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+fn parse_floats(text: &str) -> Result<Vec<f32>, String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.parse()
+                .map_err(|_| format!("expected a number, found '{word}'"))
+        })
+        .collect()
+}
+
+// Indices of a face corner into the position, texture coordinate and normal lists
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// The faces of one `o`/`g` group, with every distinct corner becoming a vertex.
+#[derive(Default)]
+struct Group {
+    corners: Vec<Corner>,
+    vertex_ids: HashMap<Corner, u32>,
+    indices: Vec<[u32; 3]>,
+    materials: Vec<Option<String>>,
+    material_ids: Vec<u32>,
+}
+
+impl Group {
+    fn vertex(&mut self, corner: Corner) -> u32 {
+        *self.vertex_ids.entry(corner).or_insert_with(|| {
+            self.corners.push(corner);
+            u32::try_from(self.corners.len() - 1).expect("mesh fits in u32 indices")
+        })
+    }
+
+    fn material_id(&mut self, material: Option<&String>) -> u32 {
+        let position = self.materials.iter().position(|m| m.as_ref() == material);
+        let id = position.unwrap_or_else(|| {
+            self.materials.push(material.cloned());
+            self.materials.len() - 1
+        });
+        u32::try_from(id).expect("material count fits in u32")
+    }
+}
+
+#[derive(Default)]
+struct ObjParser {
+    positions: Vec<Point>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Direction>,
+    library: HashMap<String, mtl::MtlMaterial>,
+    material: Option<String>,
+    groups: Vec<Group>,
+}
+
+impl ObjParser {
+    fn group(&mut self) -> &mut Group {
+        if self.groups.is_empty() {
+            self.groups.push(Group::default());
+        }
+        self.groups.last_mut().expect("at least one group")
+    }
+
+    fn statement(&mut self, keyword: &str, rest: &str, base_dir: &Path) -> Result<(), ObjError> {
+        let error = |message: String| ObjError {
+            path: PathBuf::new(),
+            line: None,
+            message,
+        };
+        match keyword {
+            "v" => match parse_floats(rest).map_err(error)?[..] {
+                // An optional w or vertex color may follow
+                [x, y, z, ..] => self.positions.push(Point::new(x, y, z)),
+                _ => return Err(error(String::from("v needs three coordinates"))),
+            },
+            "vt" => match parse_floats(rest).map_err(error)?[..] {
+                [u] => self.uvs.push(Vec2::new(u, 0.0)),
+                [u, v, ..] => self.uvs.push(Vec2::new(u, v)),
+                _ => return Err(error(String::from("vt needs texture coordinates"))),
+            },
+            "vn" => match parse_floats(rest).map_err(error)?[..] {
+                [x, y, z] => self.normals.push(Direction::new(x, y, z)),
+                _ => return Err(error(String::from("vn needs three coordinates"))),
+            },
+            "f" => self.face(rest).map_err(error)?,
+            // Consecutive group statements without faces in between share a group
+            "o" | "g" if self.groups.last().is_none_or(|g| !g.indices.is_empty()) => {
+                self.groups.push(Group::default());
+            }
+            "mtllib" => {
+                for file in rest.split_whitespace() {
+                    let path = base_dir.join(file);
+                    let source = fs::read_to_string(&path)
+                        .map_err(|e| error(format!("cannot read '{}': {e}", path.display())))?;
+                    self.library.extend(mtl::parse(&source, &path)?);
+                }
+            }
+            "usemtl" => {
+                if !self.library.contains_key(rest) {
+                    return Err(error(format!("unknown material '{rest}'")));
+                }
+                self.material = Some(rest.to_string());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn face(&mut self, rest: &str) -> Result<(), String> {
+        let corners = rest
+            .split_whitespace()
+            .map(|corner| self.corner(corner))
+            .collect::<Result<Vec<_>, _>>()?;
+        if corners.len() < 3 {
+            return Err(format!(
+                "a face needs at least three vertices, found {}",
+                corners.len()
+            ));
+        }
+
+        let material = self.material.clone();
+        let group = self.group();
+        let ids: Vec<u32> = corners.into_iter().map(|c| group.vertex(c)).collect();
+        let material_id = group.material_id(material.as_ref());
+        for pair in ids[1..].windows(2) {
+            group.indices.push([ids[0], pair[0], pair[1]]);
+            group.material_ids.push(material_id);
+        }
+        Ok(())
+    }
+
+    /// Resolves `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based indices.
+    fn corner(&self, text: &str) -> Result<Corner, String> {
+        let mut parts = text.split('/');
+        let position = parts.next().unwrap_or_default();
+        let uv = parts.next().filter(|s| !s.is_empty());
+        let normal = parts.next().filter(|s| !s.is_empty());
+        if parts.next().is_some() {
+            return Err(format!("invalid face vertex '{text}'"));
+        }
+        Ok((
+            resolve(position, self.positions.len(), "vertex")?,
+            uv.map(|i| resolve(i, self.uvs.len(), "texture coordinate"))
+                .transpose()?,
+            normal
+                .map(|i| resolve(i, self.normals.len(), "normal"))
+                .transpose()?,
+        ))
+    }
+
+    fn finish(self) -> Vec<MeshData> {
+        let default_material = Material::lambertian(Color::new(0.8, 0.8, 0.8));
+        let has_uvs = |g: &Group| g.corners.iter().any(|c| c.1.is_some());
+        let has_normals = |g: &Group| g.corners.iter().any(|c| c.2.is_some());
+        self.groups
+            .iter()
+            .filter(|group| !group.indices.is_empty())
+            .map(|group| MeshData {
+                positions: group.corners.iter().map(|c| self.positions[c.0]).collect(),
+                // Corners without a normal get a zero one, which falls back to flat shading
+                normals: if has_normals(group) {
+                    let normal = |c: &Corner| c.2.map_or(Direction::ZERO, |i| self.normals[i]);
+                    group.corners.iter().map(normal).collect()
+                } else {
+                    Vec::new()
+                },
+                uvs: if has_uvs(group) {
+                    let uv = |c: &Corner| c.1.map_or(Vec2::ZERO, |i| self.uvs[i]);
+                    group.corners.iter().map(uv).collect()
+                } else {
+                    Vec::new()
+                },
+                indices: group.indices.clone(),
+                materials: group
+                    .materials
+                    .iter()
+                    .map(|name| match name {
+                        Some(name) => self.library[name].to_material(),
+                        None => default_material.clone(),
+                    })
+                    .collect(),
+                material_ids: group.material_ids.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Turns a one-based OBJ index, or a negative one counting back from the
+/// latest element, into a zero-based index into a list of `count` elements.
+fn resolve(text: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: isize = text
+        .parse()
+        .map_err(|_| format!("invalid {what} index '{text}'"))?;
+    let resolved = match index {
+        1.. => index.unsigned_abs() - 1,
+        ..=-1 => count.wrapping_sub(index.unsigned_abs()),
+        0 => usize::MAX,
+    };
+    if resolved < count {
+        Ok(resolved)
+    } else {
+        Err(format!(
+            "{what} index {index} is out of range, {count} defined so far"
+        ))
+    }
+}
+
+/// Reads an OBJ file and the material libraries it references. Every `o` or
+/// `g` group with faces becomes its own mesh.
+pub fn load(path: &Path) -> Result<Vec<MeshData>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|e| ObjError {
+        path: path.to_path_buf(),
+        line: None,
+        message: e.to_string(),
+    })?;
+    parse(&source, path)
+}
+
+/// Parses OBJ `source` read from `path`, which material libraries are relative to.
+fn parse(source: &str, path: &Path) -> Result<Vec<MeshData>, ObjError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut parser = ObjParser::default();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        parser
+            .statement(keyword, rest.trim(), base_dir)
+            .map_err(|e| match e.line {
+                // Errors inside a material library already point into it
+                Some(_) => e,
+                None => ObjError {
+                    path: path.to_path_buf(),
+                    line: Some(index + 1),
+                    message: e.message,
+                },
+            })?;
+    }
+
+    let meshes = parser.finish();
+    if meshes.is_empty() {
+        return Err(ObjError {
+            path: path.to_path_buf(),
+            line: None,
+            message: String::from("no faces found"),
+        });
+    }
+    Ok(meshes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Where the sources are pretended to come from, next to `pyramid.mtl`.
+    fn models_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/models/test.obj")
+    }
+
+    fn parse_error(source: &str) -> ObjError {
+        match parse(source, &models_path()) {
+            Ok(_) => panic!("parsed {source:?}"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn quads_with_relative_indices_become_two_triangles() {
+        let source = "
+            v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0
+            vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1
+            vn 0 0 1
+            f -4/-4/-1 -3/-3/-1 -2/-2/-1 -1/-1/-1
+        ";
+        let meshes = parse(source, &models_path()).unwrap();
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        assert_eq!(mesh.indices, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.positions[2], Point::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.uvs[3], Vec2::new(0.0, 1.0));
+        assert!(mesh.normals.iter().all(|&n| n == Direction::Z));
+        assert_eq!(mesh.material_ids, [0, 0]);
+    }
+
+    #[test]
+    fn groups_with_faces_become_separate_meshes() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1
+            o first\nf 1 2 3\nf 1 3 4
+            o empty\ng second\nf 2 3 4
+            g third";
+        let meshes = parse(source, &models_path()).unwrap();
+        let triangles: Vec<_> = meshes.iter().map(|mesh| mesh.indices.len()).collect();
+        assert_eq!(triangles, [2, 1]);
+    }
+
+    #[test]
+    fn faces_take_the_material_in_use() {
+        let source = "mtllib pyramid.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0
+            usemtl gold\nf 1 2 3\nusemtl stone\nf 1 3 2\nusemtl gold\nf 3 2 1";
+        let meshes = parse(source, &models_path()).unwrap();
+        let mesh = &meshes[0];
+        assert_eq!(mesh.material_ids, [0, 1, 0]);
+        assert!(matches!(
+            mesh.materials[..],
+            [Material::Metal(_), Material::Lambertian(_)]
+        ));
+    }
+
+    #[test]
+    fn errors_name_the_offending_line() {
+        let e = parse_error("v 0 0 0\nv 1 0 0\nf 1 2 3");
+        assert_eq!(e.line, Some(3));
+        assert_eq!(
+            e.message,
+            "vertex index 3 is out of range, 2 defined so far"
+        );
+
+        let e = parse_error("v 0 0 0\nf 1 -2 1");
+        assert_eq!(
+            e.message,
+            "vertex index -2 is out of range, 1 defined so far"
+        );
+
+        let e = parse_error("v 0 0 0\nv 1 0 0\n# two corners\nf 1 2");
+        assert_eq!(e.line, Some(4));
+        assert_eq!(e.message, "a face needs at least three vertices, found 2");
+
+        let e = parse_error("v 0 0 0\nf 1/a 1 1");
+        assert_eq!(e.message, "invalid texture coordinate index 'a'");
+
+        let e = parse_error("v 0 0\n");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (Some(1), "v needs three coordinates")
+        );
+
+        let e = parse_error("mtllib pyramid.mtl\nusemtl glass");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (Some(2), "unknown material 'glass'")
+        );
+
+        let e = parse_error("v 0 0 0\n");
+        assert_eq!((e.line, e.message.as_str()), (None, "no faces found"));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{parse_floats, ObjError};
+use crate::color::Color;
+use crate::image::Image;
+use crate::material::Material;
+use crate::texture::{Filter, Texture, Wrap};
+
+/// The subset of a Wavefront MTL material that maps onto our materials.
+#[derive(Debug, Clone)]
+pub struct MtlMaterial {
+    diffuse: Color,                  // Kd
+    specular: Color,                 // Ks
+    emission: Color,                 // Ke
+    shininess: f32,                  // Ns
+    optical_density: Option<f32>,    // Ni
+    dissolve: f32,                   // d, or 1 - Tr
+    diffuse_map: Option<Arc<Image>>, // map_Kd, replaces Kd
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::new(0.0, 0.0, 0.0),
+            emission: Color::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            optical_density: None,
+            dissolve: 1.0,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Picks the closest of our materials: lights for emissive surfaces, glass
+    /// for transparent ones, metal when the specular color dominates and
+    /// lambertian otherwise. Phong exponents become metal fuzz.
+    pub fn to_material(&self) -> Material {
+        let luminance = |c: Color| c.dot(Color::new(0.2126, 0.7152, 0.0722));
+        if self.emission.max_element() > 0.0 {
+            Material::diffuse_light(self.emission)
+        } else if self.dissolve < 1.0 {
+            Material::dielectric(self.optical_density.unwrap_or(1.5))
+        } else if luminance(self.specular) > luminance(self.diffuse) {
+            let fuzz = (2.0 / (self.shininess + 2.0)).sqrt();
+            Material::metal(self.specular, fuzz)
+        } else if let Some(image) = &self.diffuse_map {
+            Material::lambertian_with_texture(Texture::image(
+                Arc::clone(image),
+                Filter::Bilinear,
+                Wrap::Repeat,
+            ))
+        } else {
+            Material::lambertian(self.diffuse)
+        }
+    }
+}
+
+/// Parses an MTL library and loads the images it names, relative to the library's directory.
+pub fn parse(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+    let mut images: HashMap<&str, Arc<Image>> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| ObjError {
+            path: path.to_path_buf(),
+            line: Some(index + 1),
+            message,
+        };
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((keyword, rest)) = line
+            .split_once(char::is_whitespace)
+            .or_else(|| (!line.is_empty()).then_some((line, "")))
+        else {
+            continue;
+        };
+        let rest = rest.trim();
+
+        if keyword == "newmtl" {
+            if rest.is_empty() {
+                return Err(error(String::from("newmtl needs a material name")));
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((rest.to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = current.as_mut() else {
+            return Err(error(format!("'{keyword}' before any newmtl")));
+        };
+        let color = || -> Result<Color, ObjError> {
+            match parse_floats(rest).map_err(&error)?[..] {
+                [r, g, b] => Ok(Color::new(r, g, b)),
+                [v] => Ok(Color::splat(v)),
+                _ => Err(error(format!("{keyword} needs one or three numbers"))),
+            }
+        };
+        let scalar = || -> Result<f32, ObjError> {
+            match parse_floats(rest).map_err(&error)?[..] {
+                [v] => Ok(v),
+                _ => Err(error(format!("{keyword} needs a single number"))),
+            }
+        };
+        match keyword {
+            "Kd" => material.diffuse = color()?,
+            "Ks" => material.specular = color()?,
+            "Ke" => material.emission = color()?,
+            "Ns" => material.shininess = scalar()?,
+            "Ni" => material.optical_density = Some(scalar()?),
+            "d" => material.dissolve = scalar()?,
+            "Tr" => material.dissolve = 1.0 - scalar()?,
+            "map_Kd" => {
+                // Options such as -s or -o come first, the file name is last
+                let file = rest
+                    .split_whitespace()
+                    .last()
+                    .ok_or_else(|| error(String::from("map_Kd needs a file name")))?;
+                if !images.contains_key(file) {
+                    let image_path = base_dir.join(file);
+                    let image = Image::load(&image_path).map_err(|e| {
+                        error(format!("cannot load image '{}': {e}", image_path.display()))
+                    })?;
+                    images.insert(file, Arc::new(image));
+                }
+                let image = Arc::clone(&images[file]);
+                material.diffuse_map = Some(image);
+            }
+            // Everything else (Ka, illum, bump maps, ...) has no equivalent here
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Where the libraries are pretended to come from, so `map_Kd` finds `scenes/textures`.
+    fn library_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/models/test.mtl")
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // Parsed numbers are exact
+    fn statements_map_onto_materials() {
+        let source = "
+            newmtl water
+            Kd 0.1 0.2 0.3
+            Ni 1.33
+            d 0.5
+
+            newmtl grid
+            Kd 0.5
+            map_Kd -s 2 2 1 ../textures/uv_grid.png
+
+            newmtl chrome
+            Kd 0.1
+            Ks 0.9
+            Ns 98
+
+            newmtl lamp
+            Ke 4 4 3
+        ";
+        let materials = parse(source, &library_path()).unwrap();
+
+        let water = &materials["water"];
+        assert_eq!(water.diffuse, Color::new(0.1, 0.2, 0.3));
+        assert_eq!((water.optical_density, water.dissolve), (Some(1.33), 0.5));
+        assert!(matches!(water.to_material(), Material::Dielectric(_)));
+
+        let grid = &materials["grid"];
+        assert_eq!(grid.diffuse, Color::splat(0.5));
+        assert!(grid.diffuse_map.is_some());
+        assert!(matches!(grid.to_material(), Material::Lambertian(_)));
+
+        let chrome = &materials["chrome"];
+        assert_eq!(chrome.shininess, 98.0);
+        assert!(matches!(chrome.to_material(), Material::Metal(_)));
+
+        assert!(matches!(
+            materials["lamp"].to_material(),
+            Material::DiffuseLight(_)
+        ));
+    }
+
+    #[test]
+    fn errors_name_the_offending_line() {
+        let error = |source: &str| parse(source, &library_path()).unwrap_err();
+
+        let e = error("Kd 1 1 1");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (Some(1), "'Kd' before any newmtl")
+        );
+
+        let e = error("newmtl a\nKd 1 1");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (Some(2), "Kd needs one or three numbers")
+        );
+
+        let e = error("newmtl a\n\nNi high");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (Some(3), "expected a number, found 'high'")
+        );
+
+        let e = error("newmtl a\nmap_Kd missing.png");
+        assert_eq!(e.line, Some(2));
+        assert!(e.message.starts_with("cannot load image"), "{}", e.message);
+    }
+}
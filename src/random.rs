@@ -1,33 +1,451 @@
-use glam::Vec3;
-use rand::{distributions::uniform::SampleUniform, Rng};
+use std::f32::consts::{FRAC_1_PI, FRAC_PI_2, FRAC_PI_4, PI, TAU};
 
-pub fn number<T: SampleUniform + PartialOrd>(min: T, max: T) -> T {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(min..max)
+use glam::{Vec2, Vec3};
+use rand::{distributions::uniform::SampleUniform, Rng, RngCore};
+
+use crate::utility::Onb;
+
+/// A seedable PCG32 generator. Every camera sample gets its own sampler derived
+/// from the render seed, the pixel and the sample index, so a render does not
+/// depend on which thread happened to trace which pixel.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+    increment: u64,
 }
 
-pub fn vec3(min: f32, max: f32) -> Vec3 {
-    Vec3::new(number(min, max), number(min, max), number(min, max))
+impl Sampler {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    pub const fn new(seed: u64) -> Self {
+        let mut sampler = Self {
+            state: 0,
+            increment: (splitmix64(seed) << 1) | 1,
+        };
+        sampler.step();
+        sampler.state = sampler.state.wrapping_add(seed);
+        sampler.step();
+        sampler
+    }
+
+    pub fn for_sample(seed: u64, i: u16, j: u16, sample: u16) -> Self {
+        let key = u64::from(i) | u64::from(j) << 16 | u64::from(sample) << 32;
+        Self::new(seed ^ splitmix64(key))
+    }
+
+    const fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
 }
 
-pub fn vec3_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new(number(-1.0, 1.0), number(-1.0, 1.0), 0.0);
-        if p.length_squared() < 1.0 {
-            return p;
+#[allow(clippy::cast_possible_truncation)]
+impl RngCore for Sampler {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+    fn next_u64(&mut self) -> u64 {
+        u64::from(self.next_u32()) | u64::from(self.next_u32()) << 32
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
         }
     }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
 }
 
-pub fn vec3_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = vec3(-1.0, 1.0);
-        if p.length_squared() < 1.0 {
-            return p;
-        }
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn number<T: SampleUniform + PartialOrd>(rng: &mut Sampler, min: T, max: T) -> T {
+    rng.gen_range(min..max)
+}
+
+pub fn vec3(rng: &mut Sampler, min: f32, max: f32) -> Vec3 {
+    Vec3::new(
+        number(rng, min, max),
+        number(rng, min, max),
+        number(rng, min, max),
+    )
+}
+
+/// A point in the unit disk, from the concentric map of Shirley and Chiu, which
+/// keeps neighbouring samples of the square neighbours in the disk.
+/// Returns the point and its density per unit area.
+pub fn concentric_disk(rng: &mut Sampler) -> (Vec2, f32) {
+    let offset = Vec2::new(number(rng, -1.0, 1.0), number(rng, -1.0, 1.0));
+    if offset == Vec2::ZERO {
+        return (Vec2::ZERO, FRAC_1_PI);
     }
+    let (radius, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, FRAC_PI_4 * (offset.y / offset.x))
+    } else {
+        (offset.y, FRAC_PI_4.mul_add(-offset.x / offset.y, FRAC_PI_2))
+    };
+    (Vec2::new(theta.cos(), theta.sin()) * radius, FRAC_1_PI)
+}
+
+/// A direction on the whole unit sphere and its density per unit solid angle.
+pub fn uniform_sphere(rng: &mut Sampler) -> (Vec3, f32) {
+    let z = number(rng, -1.0f32, 1.0);
+    (around_z(z, rng), 1.0 / (4.0 * PI))
+}
+
+/// A direction in the hemisphere around +z with density cos(theta) / pi.
+pub fn cosine_hemisphere(rng: &mut Sampler) -> (Vec3, f32) {
+    // Points spread evenly over the disk, lifted onto the hemisphere
+    lift_to_hemisphere(concentric_disk(rng).0)
+}
+
+/// The direction above a point of the unit disk, with its cosine density.
+/// Points on the rim, which rounding can push just outside, are kept a hair
+/// above the horizon so the density never reaches zero.
+fn lift_to_hemisphere(disk: Vec2) -> (Vec3, f32) {
+    let z = (1.0 - disk.length_squared()).max(f32::EPSILON).sqrt();
+    (disk.extend(z), z * FRAC_1_PI)
+}
+
+/// A direction scattered by the Henyey-Greenstein phase function from light
+/// travelling along +z, and its density per unit solid angle. The asymmetry
+/// `g` in (-1, 1) is the mean cosine: forward scattering for g > 0, backward for g < 0.
+pub fn henyey_greenstein(rng: &mut Sampler, g: f32) -> (Vec3, f32) {
+    let u: f32 = number(rng, 0.0, 1.0);
+    let cos_theta = if g.abs() < 1e-3 {
+        2.0f32.mul_add(u, -1.0)
+    } else {
+        // Inverts the cumulative distribution of cos(theta)
+        let ratio = g.mul_add(-g, 1.0) / (2.0 * g).mul_add(u, 1.0 - g);
+        ratio.mul_add(-ratio, g.mul_add(g, 1.0)) / (2.0 * g)
+    };
+    let cos_theta = cos_theta.clamp(-1.0, 1.0);
+    (
+        around_z(cos_theta, rng),
+        henyey_greenstein_pdf(cos_theta, g),
+    )
+}
+
+/// Density of [`henyey_greenstein`] for a direction at `cos_theta` to the
+/// direction of travel.
+pub fn henyey_greenstein_pdf(cos_theta: f32, g: f32) -> f32 {
+    let denominator = (2.0 * g).mul_add(-cos_theta, g.mul_add(g, 1.0));
+    g.mul_add(-g, 1.0) / (4.0 * PI * denominator * denominator.sqrt())
+}
+
+/// A point spread evenly over the triangle `abc` and its density per unit area.
+pub fn triangle(rng: &mut Sampler, [a, b, c]: [Vec3; 3]) -> (Vec3, f32) {
+    // Folding the unit square onto the triangle with a square root keeps the density even
+    let spread = number(rng, 0.0f32, 1.0).sqrt();
+    let blend = number(rng, 0.0, 1.0);
+    let point = a * (1.0 - spread) + b * (spread * (1.0 - blend)) + c * (spread * blend);
+    (point, 2.0 / (b - a).cross(c - a).length())
+}
+
+/// A direction from `origin` toward the part of a sphere visible from there,
+/// spread evenly over the cone it covers. Returns the unit direction and its
+/// density per unit solid angle, or `None` when `origin` is inside the sphere.
+pub fn sphere_from_point(
+    rng: &mut Sampler,
+    center: Vec3,
+    radius: f32,
+    origin: Vec3,
+) -> Option<(Vec3, f32)> {
+    let offset = center - origin;
+    let distance_squared = offset.length_squared();
+    let radius_squared = radius * radius;
+    if distance_squared <= radius_squared {
+        return None;
+    }
+    let (local, pdf) = cone_with_height(rng, cone_height(radius_squared / distance_squared));
+    Some((
+        Onb::new(offset / distance_squared.sqrt()).to_world(local),
+        pdf,
+    ))
+}
+
+/// 1 - cos(theta) of the cone a sphere covers, given sin^2(theta). Written so
+/// it stays accurate for small, distant spheres.
+pub fn cone_height(sin_squared_max: f32) -> f32 {
+    sin_squared_max / (1.0 + (1.0 - sin_squared_max).sqrt())
+}
+
+fn cone_with_height(rng: &mut Sampler, height: f32) -> (Vec3, f32) {
+    let z = 1.0 - number(rng, 0.0, 1.0) * height;
+    (around_z(z, rng), 1.0 / (TAU * height))
+}
+
+/// The unit vector at height `z` and a random angle around the z axis.
+fn around_z(z: f32, rng: &mut Sampler) -> Vec3 {
+    let radius = z.mul_add(-z, 1.0).max(0.0).sqrt();
+    let phi = number(rng, 0.0, TAU);
+    Vec3::new(radius * phi.cos(), radius * phi.sin(), z)
+}
+
+/// A direction in the hemisphere around +z and its density per unit solid
+/// angle. Tests integrate BSDFs with it.
+#[cfg(test)]
+pub fn uniform_hemisphere(rng: &mut Sampler) -> (Vec3, f32) {
+    let z = number(rng, 0.0f32, 1.0);
+    (around_z(z, rng), 1.0 / TAU)
 }
 
-pub fn unit_vector() -> Vec3 {
-    vec3_in_unit_sphere().normalize()
+/// A direction spread evenly over the cone around +z out to `cos_theta_max`,
+/// and its density per unit solid angle. Checks the cone the sphere sampling uses.
+#[cfg(test)]
+pub fn uniform_cone(rng: &mut Sampler, cos_theta_max: f32) -> (Vec3, f32) {
+    cone_with_height(rng, 1.0 - cos_theta_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 100_000;
+
+    /// Averages `f / pdf` over many samples, the Monte Carlo estimate of the
+    /// integral of `f` over the sampled domain.
+    fn estimate(mut sample: impl FnMut(&mut Sampler) -> (f32, f32)) -> f32 {
+        let mut rng = Sampler::new(7);
+        let sum: f64 = (0..SAMPLES)
+            .map(|_| {
+                let (f, pdf) = sample(&mut rng);
+                f64::from(f / pdf)
+            })
+            .sum();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+        let mean = (sum / SAMPLES as f64) as f32;
+        mean
+    }
+
+    /// Pearson's chi-square test that values which should be uniform in [0, 1)
+    /// fall evenly into 20 bins. The bound is the 0.1% tail for 19 degrees of freedom.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn assert_uniform(mut value: impl FnMut(&mut Sampler) -> f32) {
+        const BINS: usize = 20;
+        let mut rng = Sampler::new(11);
+        let mut counts = [0usize; BINS];
+        for _ in 0..SAMPLES {
+            let x = value(&mut rng);
+            assert!((0.0..=1.0).contains(&x), "{x} is outside [0, 1]");
+            counts[((x * BINS as f32) as usize).min(BINS - 1)] += 1;
+        }
+        let expected = SAMPLES as f32 / BINS as f32;
+        let chi_square: f32 = counts
+            .iter()
+            .map(|&count| (count as f32 - expected).powi(2) / expected)
+            .sum();
+        assert!(chi_square < 43.8, "chi square {chi_square} for {counts:?}");
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01 * expected,
+            "{actual} is not within 1% of {expected}"
+        );
+    }
+
+    fn azimuth(direction: Vec3) -> f32 {
+        (direction.y.atan2(direction.x) + PI) / TAU
+    }
+
+    #[test]
+    fn concentric_disk_covers_the_disk_evenly() {
+        assert_close(estimate(|rng| (1.0, concentric_disk(rng).1)), PI);
+        assert_uniform(|rng| concentric_disk(rng).0.length_squared());
+        assert_uniform(|rng| azimuth(concentric_disk(rng).0.extend(0.0)));
+    }
+
+    #[test]
+    fn uniform_sphere_covers_the_sphere_evenly() {
+        assert_close(estimate(|rng| (1.0, uniform_sphere(rng).1)), 4.0 * PI);
+        assert_uniform(|rng| uniform_sphere(rng).0.z.mul_add(0.5, 0.5));
+        assert_uniform(|rng| azimuth(uniform_sphere(rng).0));
+        let mut rng = Sampler::new(3);
+        assert!((uniform_sphere(&mut rng).0.length() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn uniform_hemisphere_covers_the_upper_half_evenly() {
+        assert_close(estimate(|rng| (1.0, uniform_hemisphere(rng).1)), TAU);
+        assert_uniform(|rng| uniform_hemisphere(rng).0.z);
+        assert_uniform(|rng| azimuth(uniform_hemisphere(rng).0));
+    }
+
+    #[test]
+    fn cosine_hemisphere_density_matches_its_samples() {
+        // The integral of cos^2 over the hemisphere is 2 pi / 3
+        assert_close(
+            estimate(|rng| {
+                let (direction, pdf) = cosine_hemisphere(rng);
+                (direction.z * direction.z, pdf)
+            }),
+            TAU / 3.0,
+        );
+        // With density cos / pi, cos^2 is uniform in [0, 1]
+        assert_uniform(|rng| cosine_hemisphere(rng).0.z.powi(2));
+        assert_uniform(|rng| azimuth(cosine_hemisphere(rng).0));
+        let mut rng = Sampler::new(5);
+        for _ in 0..1000 {
+            let (direction, pdf) = cosine_hemisphere(&mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-5);
+            assert!((pdf - direction.z / PI).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn cosine_hemisphere_rim_keeps_a_positive_density() {
+        // Unit length points and ones rounded just past the rim, as the
+        // concentric map produces for offsets of exactly -1
+        for i in 0..1000 {
+            let angle = i as f32 / 1000.0 * TAU;
+            for disk in [
+                Vec2::from_angle(angle),
+                Vec2::from_angle(angle) * 1.000_000_1,
+            ] {
+                let (direction, pdf) = lift_to_hemisphere(disk);
+                assert!(direction.z > 0.0 && pdf > 0.0, "{disk}: {direction}, {pdf}");
+                assert!((direction.length() - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_cone_stays_inside_the_cone() {
+        let cos_theta_max = 0.8;
+        assert_close(
+            estimate(|rng| (1.0, uniform_cone(rng, cos_theta_max).1)),
+            TAU * (1.0 - cos_theta_max),
+        );
+        assert_uniform(|rng| (uniform_cone(rng, cos_theta_max).0.z - cos_theta_max) / 0.2);
+    }
+
+    #[test]
+    fn henyey_greenstein_density_matches_its_samples() {
+        for g in [-0.5, 0.0, 0.3, 0.8] {
+            // The density integrates to one over the sphere
+            assert_close(
+                estimate(|rng| {
+                    let (direction, pdf) = uniform_sphere(rng);
+                    (henyey_greenstein_pdf(direction.z, g), pdf)
+                }),
+                1.0,
+            );
+            // Its cumulative distribution maps the samples' cosines onto [0, 1] evenly
+            let cdf = |cos_theta: f32| {
+                if g == 0.0 {
+                    cos_theta.mul_add(0.5, 0.5)
+                } else {
+                    let root = (2.0 * g).mul_add(-cos_theta, g.mul_add(g, 1.0)).sqrt();
+                    g.mul_add(-g, 1.0) / (2.0 * g) * (1.0 / root - 1.0 / (1.0 + g))
+                }
+            };
+            assert_uniform(|rng| cdf(henyey_greenstein(rng, g).0.z).clamp(0.0, 1.0));
+            assert_uniform(|rng| azimuth(henyey_greenstein(rng, g).0));
+
+            let mut rng = Sampler::new(23);
+            #[allow(clippy::cast_precision_loss)]
+            let mean_cosine = (0..SAMPLES)
+                .map(|_| henyey_greenstein(&mut rng, g).0.z)
+                .sum::<f32>()
+                / SAMPLES as f32;
+            assert!(
+                (mean_cosine - g).abs() < 0.01,
+                "mean cosine {mean_cosine} for g {g}"
+            );
+            let (direction, pdf) = henyey_greenstein(&mut rng, g);
+            assert!((pdf - henyey_greenstein_pdf(direction.z, g)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn triangle_covers_the_triangle_evenly() {
+        let vertices = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(3.0, 1.0, 0.0),
+            Vec3::new(1.0, 2.0, 2.0),
+        ];
+        let [a, b, c] = vertices;
+        let area = 0.5 * (b - a).cross(c - a).length();
+        assert_close(estimate(|rng| (1.0, triangle(rng, vertices).1)), area);
+
+        // Lines through the edge midpoints cut the triangle into four parts of
+        // equal area: one at each corner, where that corner's weight is over
+        // a half, and one in the middle
+        let mut rng = Sampler::new(13);
+        let mut counts = [0usize; 4];
+        let doubled_area = (b - a).cross(c - a).length();
+        for _ in 0..SAMPLES {
+            let (point, _) = triangle(&mut rng, vertices);
+            let weights = [(b, c), (c, a), (a, b)]
+                .map(|(q, r)| (q - point).cross(r - point).length() / doubled_area);
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            counts[weights.iter().position(|&w| w > 0.5).unwrap_or(3)] += 1;
+        }
+        for count in counts {
+            #[allow(clippy::cast_precision_loss)]
+            let fraction = count as f32 / SAMPLES as f32;
+            assert!((fraction - 0.25).abs() < 0.01, "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn sphere_from_point_only_picks_directions_that_hit() {
+        let center = Vec3::new(0.0, 1.0, -4.0);
+        let radius = 0.5;
+        let origin = Vec3::new(0.5, 0.0, 0.0);
+        let distance_squared = (center - origin).length_squared();
+        let solid_angle = TAU * cone_height(radius * radius / distance_squared);
+        assert_close(
+            estimate(|rng| {
+                (
+                    1.0,
+                    sphere_from_point(rng, center, radius, origin).unwrap().1,
+                )
+            }),
+            solid_angle,
+        );
+
+        let mut rng = Sampler::new(17);
+        for _ in 0..1000 {
+            let (direction, _) = sphere_from_point(&mut rng, center, radius, origin).unwrap();
+            let offset = center - origin;
+            let closest = offset - direction * offset.dot(direction);
+            assert!(closest.length() <= radius * 1.0001);
+        }
+        assert!(sphere_from_point(&mut rng, center, radius, center).is_none());
+    }
+
+    #[test]
+    fn onb_is_orthonormal_and_round_trips() {
+        let mut rng = Sampler::new(19);
+        for _ in 0..100 {
+            let (w, _) = uniform_sphere(&mut rng);
+            let onb = Onb::new(w);
+            for (x, y) in [(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+                assert!(x.dot(y).abs() < 1e-5);
+                assert!((x.length() - 1.0).abs() < 1e-5);
+            }
+            let (local, _) = uniform_sphere(&mut rng);
+            assert!(onb.to_local(onb.to_world(local)).abs_diff_eq(local, 1e-5));
+            assert!(onb.to_world(Vec3::Z).abs_diff_eq(w, 1e-6));
+        }
+    }
 }
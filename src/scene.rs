@@ -0,0 +1,564 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hittable::{HittableList, HittableObject, MeshData};
+use crate::material::Material;
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Point};
+use crate::texture::{NoiseKind, Texture};
+
+use glam::{Affine3A, Quat, Vec2};
+
+mod parser;
+
+pub use parser::parse;
+
+/// A world to render together with the camera set up to look at it.
+pub struct Scene {
+    pub world: HittableObject,
+    pub camera: Camera,
+}
+
+type SceneFn = fn(&mut Sampler) -> Scene;
+
+/// Every built-in scene with its command-line name and a short description.
+pub const SCENES: [(&str, &str, SceneFn); 8] = [
+    (
+        "cornell-box",
+        "The Cornell box: two blocks in a closed room lit from the ceiling",
+        cornell_box,
+    ),
+    (
+        "cornell-smoke",
+        "The Cornell box with blocks of dark smoke and white fog",
+        cornell_smoke,
+    ),
+    (
+        "perlin-spheres",
+        "Smooth, turbulent and marble Perlin noise spheres on a marble floor",
+        perlin_spheres,
+    ),
+    (
+        "quads",
+        "Five colored quads facing the camera from different sides",
+        quads,
+    ),
+    (
+        "random-spheres",
+        "Final scene of the first book: a field of small random spheres",
+        random_spheres,
+    ),
+    (
+        "simple-light",
+        "A sphere lit only by a glowing sphere above it, against a black sky",
+        simple_light,
+    ),
+    (
+        "torus",
+        "A smooth shaded triangle mesh torus and a flat triangle on a checker floor",
+        torus,
+    ),
+    (
+        "two-spheres",
+        "Two checker textured spheres touching at the origin",
+        two_spheres,
+    ),
+];
+
+pub fn find(name: &str) -> Option<SceneFn> {
+    SCENES
+        .iter()
+        .find(|(scene_name, _, _)| *scene_name == name)
+        .map(|&(_, _, build)| build)
+}
+
+/// Reads and parses a scene description file, see [`parser`] for the format.
+pub fn load(path: &Path) -> Result<Scene, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let (world, camera) =
+        parse(&source, base_dir).map_err(|e| format!("{}:{e}", path.display()))?;
+    if world.objects.is_empty() {
+        return Err(format!("{}: the scene has no objects", path.display()));
+    }
+    let world = HittableObject::bvh(world.objects);
+    Ok(Scene { world, camera })
+}
+
+pub fn names() -> Vec<&'static str> {
+    SCENES.iter().map(|&(name, _, _)| name).collect()
+}
+
+/// The objects of the random-spheres scene, before they are put in a BVH.
+pub fn random_spheres_world(rng: &mut Sampler) -> HittableList {
+    let mut world = HittableList::default();
+
+    let checker = Texture::checker(0.32, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+
+    world.add(HittableObject::sphere(
+        Point::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Material::lambertian_with_texture(checker),
+    ));
+
+    // let ground_material = Material::lambertian(Color::new(0.5, 0.5, 0.5));
+    // world.add(HittableObject::sphere(
+    //     Point::new(0.0, -1000.0, 0.0),
+    //     1000.0,
+    //     ground_material,
+    // ));
+
+    for a in -11..11i8 {
+        for b in -11..11i8 {
+            let choose_mat = random::number(rng, 0.0, 1.0);
+            let center = Point::new(
+                0.9f32.mul_add(random::number(rng, 0.0, 1.0), f32::from(a)),
+                0.2,
+                0.9f32.mul_add(random::number(rng, 0.0, 1.0), f32::from(b)),
+            );
+            if (center - Point::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                // let mut sphere_material = Material::default();
+
+                if choose_mat < 0.8 {
+                    // diffuse
+                    let albedo = random::vec3(rng, 0.0, 1.0) * random::vec3(rng, 0.0, 1.0);
+                    let sphere_material = Material::lambertian(albedo);
+                    let center2 = center + Direction::new(0.0, random::number(rng, 0.0, 0.5), 0.0);
+                    world.add(HittableObject::moving_sphere(
+                        center,
+                        center2,
+                        0.2,
+                        sphere_material,
+                    ));
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = random::vec3(rng, 0.5, 1.0);
+                    let fuzz: f32 = random::number(rng, 0.0, 0.5);
+                    let sphere_material = Material::metal(albedo, fuzz);
+                    world.add(HittableObject::sphere(center, 0.2, sphere_material));
+                } else {
+                    // glass
+                    let sphere_material = Material::dielectric(1.5);
+                    world.add(HittableObject::sphere(center, 0.2, sphere_material));
+                }
+            }
+        }
+    }
+
+    let material1 = Material::dielectric(1.5);
+    world.add(HittableObject::sphere(
+        Point::new(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    ));
+
+    let material2 = Material::lambertian(Color::new(0.4, 0.2, 0.1));
+    world.add(HittableObject::sphere(
+        Point::new(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    ));
+
+    let material3 = Material::metal(Color::new(0.7, 0.6, 0.5), 0.0);
+    world.add(HittableObject::sphere(
+        Point::new(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    ));
+
+    world
+}
+
+fn random_spheres(rng: &mut Sampler) -> Scene {
+    let world = random_spheres_world(rng);
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 20.0;
+    cam.look_from = Point::new(13.0, 2.0, 3.0);
+    cam.look_at = Point::new(0.0, 0.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.6;
+    cam.focus_dist = 10.0;
+
+    Scene { world, camera: cam }
+}
+
+fn two_spheres(_rng: &mut Sampler) -> Scene {
+    let mut world = HittableList::default();
+
+    let checker = Texture::checker(0.8, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+
+    world.add(HittableObject::sphere(
+        Point::new(0.0, -10.0, 0.0),
+        10.0,
+        Material::lambertian_with_texture(checker.clone()),
+    ));
+    world.add(HittableObject::sphere(
+        Point::new(0.0, 10.0, 0.0),
+        10.0,
+        Material::lambertian_with_texture(checker),
+    ));
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400u16;
+    cam.samples_per_pixel = 100u16;
+    cam.max_depth = 50u16;
+
+    cam.vfov = 20.0;
+    cam.look_from = Point::new(13.0, 2.0, 3.0);
+    cam.look_at = Point::new(0.0, 0.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
+
+fn simple_light(_rng: &mut Sampler) -> Scene {
+    let mut world = HittableList::default();
+
+    let checker = Texture::checker(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+    world.add(HittableObject::sphere(
+        Point::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Material::lambertian_with_texture(checker),
+    ));
+    world.add(HittableObject::sphere(
+        Point::new(0.0, 2.0, 0.0),
+        2.0,
+        Material::lambertian(Color::new(0.8, 0.3, 0.2)),
+    ));
+
+    let light = Material::diffuse_light(Color::new(4.0, 4.0, 4.0));
+    world.add(HittableObject::sphere(
+        Point::new(0.0, 7.0, 0.0),
+        2.0,
+        light,
+    ));
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.background = Background::solid(Color::new(0.0, 0.0, 0.0));
+
+    cam.vfov = 20.0;
+    cam.look_from = Point::new(26.0, 3.0, 6.0);
+    cam.look_at = Point::new(0.0, 2.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
+
+fn perlin_spheres(rng: &mut Sampler) -> Scene {
+    let mut world = HittableList::default();
+
+    let marble = Texture::noise(NoiseKind::Marble, 4.0, 7, rng);
+    world.add(HittableObject::sphere(
+        Point::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Material::lambertian_with_texture(marble),
+    ));
+
+    let kinds = [NoiseKind::Smooth, NoiseKind::Turbulence, NoiseKind::Marble];
+    for (kind, z) in kinds.into_iter().zip([-2.5, 0.0, 2.5]) {
+        let texture = Texture::noise(kind, 4.0, 7, rng);
+        world.add(HittableObject::sphere(
+            Point::new(0.0, 1.0, z),
+            1.0,
+            Material::lambertian_with_texture(texture),
+        ));
+    }
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 30.0;
+    cam.look_from = Point::new(13.0, 2.0, 3.0);
+    cam.look_at = Point::new(0.0, 1.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
+
+fn quads(_rng: &mut Sampler) -> Scene {
+    let mut world = HittableList::default();
+
+    // Materials
+    let left_red = Material::lambertian(Color::new(1.0, 0.2, 0.2));
+    let back_green = Material::lambertian(Color::new(0.2, 1.0, 0.2));
+    let right_blue = Material::lambertian(Color::new(0.2, 0.2, 1.0));
+    let upper_orange = Material::lambertian(Color::new(1.0, 0.5, 0.0));
+    let lower_teal = Material::lambertian(Color::new(0.2, 0.8, 0.8));
+
+    // Quads
+    world.add(HittableObject::quad(
+        Point::new(-3.0, -2.0, 5.0),
+        Direction::new(0.0, 0.0, -4.0),
+        Direction::new(0.0, 4.0, 0.0),
+        left_red,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(-2.0, -2.0, 0.0),
+        Direction::new(4.0, 0.0, 0.0),
+        Direction::new(0.0, 4.0, 0.0),
+        back_green,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(3.0, -2.0, 1.0),
+        Direction::new(0.0, 0.0, 4.0),
+        Direction::new(0.0, 4.0, 0.0),
+        right_blue,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(-2.0, 3.0, 1.0),
+        Direction::new(4.0, 0.0, 0.0),
+        Direction::new(0.0, 0.0, 4.0),
+        upper_orange,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(-2.0, -3.0, 5.0),
+        Direction::new(4.0, 0.0, 0.0),
+        Direction::new(0.0, 0.0, -4.0),
+        lower_teal,
+    ));
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 80.0;
+    cam.look_from = Point::new(0.0, 0.0, 9.0);
+    cam.look_at = Point::new(0.0, 0.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
+
+fn cornell_box(_rng: &mut Sampler) -> Scene {
+    let white = Material::lambertian(Color::new(0.73, 0.73, 0.73));
+    cornell_room(cornell_blocks(&white))
+}
+
+/// The Cornell box with its blocks turned into a dark smoke and a white fog.
+fn cornell_smoke(_rng: &mut Sampler) -> Scene {
+    let white = Material::lambertian(Color::new(0.73, 0.73, 0.73));
+    let [tall, short] = cornell_blocks(&white);
+    let smoke = Material::isotropic(Texture::solid_color(Color::new(0.0, 0.0, 0.0)));
+    let fog = Material::isotropic(Texture::solid_color(Color::new(1.0, 1.0, 1.0)));
+    cornell_room([
+        HittableObject::constant_medium(tall, 0.01, smoke),
+        HittableObject::constant_medium(short, 0.01, fog),
+    ])
+}
+
+/// The two blocks of the Cornell box, built at the origin, then turned about
+/// their corner and moved into place.
+fn cornell_blocks(white: &Material) -> [HittableObject; 2] {
+    let box1 = HittableObject::cuboid(
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(165.0, 330.0, 165.0),
+        white,
+    );
+    let box2 = HittableObject::cuboid(
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(165.0, 165.0, 165.0),
+        white,
+    );
+    [
+        HittableObject::instance(
+            Arc::new(HittableObject::bvh(box1.objects)),
+            Affine3A::from_rotation_translation(
+                Quat::from_rotation_y(15_f32.to_radians()),
+                Direction::new(265.0, 0.0, 295.0),
+            ),
+        ),
+        HittableObject::instance(
+            Arc::new(HittableObject::bvh(box2.objects)),
+            Affine3A::from_rotation_translation(
+                Quat::from_rotation_y(-18_f32.to_radians()),
+                Direction::new(130.0, 0.0, 65.0),
+            ),
+        ),
+    ]
+}
+
+/// The walls and ceiling light of the Cornell box around `contents`.
+fn cornell_room(contents: [HittableObject; 2]) -> Scene {
+    let mut world = HittableList::default();
+
+    let red = Material::lambertian(Color::new(0.65, 0.05, 0.05));
+    let white = Material::lambertian(Color::new(0.73, 0.73, 0.73));
+    let green = Material::lambertian(Color::new(0.12, 0.45, 0.15));
+    let light = Material::diffuse_light(Color::new(15.0, 15.0, 15.0));
+
+    world.add(HittableObject::quad(
+        Point::new(555.0, 0.0, 0.0),
+        Direction::new(0.0, 555.0, 0.0),
+        Direction::new(0.0, 0.0, 555.0),
+        green,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(0.0, 0.0, 0.0),
+        Direction::new(0.0, 555.0, 0.0),
+        Direction::new(0.0, 0.0, 555.0),
+        red,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(343.0, 554.0, 332.0),
+        Direction::new(-130.0, 0.0, 0.0),
+        Direction::new(0.0, 0.0, -105.0),
+        light,
+    ));
+    world.add(HittableObject::quad(
+        Point::new(0.0, 0.0, 0.0),
+        Direction::new(555.0, 0.0, 0.0),
+        Direction::new(0.0, 0.0, 555.0),
+        white.clone(),
+    ));
+    world.add(HittableObject::quad(
+        Point::new(555.0, 555.0, 555.0),
+        Direction::new(-555.0, 0.0, 0.0),
+        Direction::new(0.0, 0.0, -555.0),
+        white.clone(),
+    ));
+    world.add(HittableObject::quad(
+        Point::new(0.0, 0.0, 555.0),
+        Direction::new(555.0, 0.0, 0.0),
+        Direction::new(0.0, 555.0, 0.0),
+        white,
+    ));
+
+    for object in contents {
+        world.add(object);
+    }
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 50;
+    cam.background = Background::solid(Color::new(0.0, 0.0, 0.0));
+
+    cam.vfov = 40.0;
+    cam.look_from = Point::new(278.0, 278.0, -800.0);
+    cam.look_at = Point::new(278.0, 278.0, 0.0);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
+
+/// Builds a torus around the y axis with smooth normals and wrapped UVs.
+#[allow(clippy::cast_precision_loss)]
+pub fn torus_mesh(major_radius: f32, minor_radius: f32, rings: u32, sides: u32) -> MeshData {
+    let mut mesh = MeshData::default();
+    for i in 0..=rings {
+        let u = i as f32 / rings as f32;
+        let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+        for j in 0..=sides {
+            let v = j as f32 / sides as f32;
+            let (sin_theta, cos_theta) = (v * std::f32::consts::TAU).sin_cos();
+            let normal = Direction::new(cos_theta * cos_phi, sin_theta, cos_theta * sin_phi);
+            let ring_center = Point::new(major_radius * cos_phi, 0.0, major_radius * sin_phi);
+            mesh.positions.push(ring_center + normal * minor_radius);
+            mesh.normals.push(normal);
+            mesh.uvs.push(Vec2::new(u, v));
+        }
+    }
+    let stride = sides + 1;
+    for i in 0..rings {
+        for j in 0..sides {
+            let a = i * stride + j;
+            let b = (i + 1) * stride + j;
+            mesh.indices.push([a, a + 1, b]);
+            mesh.indices.push([b, a + 1, b + 1]);
+        }
+    }
+    mesh
+}
+
+fn torus(_rng: &mut Sampler) -> Scene {
+    let mut world = HittableList::default();
+
+    let checker = Texture::checker(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+    world.add(HittableObject::sphere(
+        Point::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Material::lambertian_with_texture(checker),
+    ));
+
+    let mut torus = torus_mesh(1.5, 0.5, 64, 32);
+    for p in &mut torus.positions {
+        p.y += 0.5;
+    }
+    torus
+        .materials
+        .push(Material::metal(Color::new(0.8, 0.6, 0.2), 0.1));
+    world.add(HittableObject::mesh(torus));
+
+    world.add(HittableObject::triangle(
+        Point::new(-4.0, 0.0, -2.0),
+        Point::new(-2.0, 0.0, -4.0),
+        Point::new(-3.0, 3.0, -3.0),
+        Material::lambertian(Color::new(0.2, 0.4, 0.8)),
+    ));
+
+    let world = HittableObject::bvh(world.objects);
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 30.0;
+    cam.look_from = Point::new(8.0, 5.0, 8.0);
+    cam.look_at = Point::new(-0.5, 0.5, -0.5);
+    cam.vup = Direction::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    Scene { world, camera: cam }
+}
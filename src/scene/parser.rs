@@ -0,0 +1,1047 @@
+//! Plain-text scene descriptions.
+//!
+//! A scene file is a list of blocks. Whitespace and line breaks are not
+//! significant and `#` starts a comment that runs to the end of the line.
+//!
+//! ```text
+//! camera {
+//!     width 400            # image width in pixels
+//!     aspect 16:9          # a number or W:H
+//!     spp 100              # samples per pixel
+//!     max_depth 50
+//!     roulette_depth 3     # bounces before Russian roulette may end a path
+//!     integrator path      # path, bsdf or normals
+//!     vfov 20              # vertical field of view in degrees
+//!     look_from 13 2 3
+//!     look_at 0 0 0
+//!     vup 0 1 0
+//!     defocus_angle 0.6
+//!     focus_dist 10
+//! }
+//!
+//! background {
+//!     type gradient        # solid, gradient or environment
+//!     color 0 0 0          # solid: the color in every direction
+//!     bottom 1 1 1         # gradient: blended from straight down to straight up
+//!     top 0.5 0.7 1
+//!     file sky.hdr         # environment: a latitude/longitude image, see image textures
+//!     rotation 90          # environment: degrees about the vertical axis
+//!     intensity 1          # environment: multiplier for the image
+//! }
+//!
+//! texture ground {
+//!     type checker         # solid, checker, noise or image
+//!     scale 0.32
+//!     even 0.2 0.3 0.1     # a color or the name of another texture
+//!     odd 0.9 0.9 0.9
+//! }
+//! texture stone {
+//!     type noise           # Perlin noise
+//!     mode marble          # smooth, turbulence or marble
+//!     scale 4              # frequency of the noise
+//!     octaves 7            # turbulence and marble: layers of finer noise
+//!     seed 1               # optional, picks a different noise pattern
+//! }
+//! texture earth {
+//!     type image           # a .png, .ppm, .hdr or .pfm file mapped by the hit's u, v
+//!     file earth.png
+//!     filter bilinear      # nearest or bilinear (default)
+//!     wrap repeat          # repeat (default), clamp or mirror
+//!     linear               # optional, the file holds data rather than sRGB colors
+//! }
+//!
+//! material floor {
+//!     type lambertian      # lambertian, metal, conductor, dielectric,
+//!                          # diffuse_light, isotropic or henyey_greenstein
+//!     texture ground       # lambertian: `texture <name>` or `albedo r g b`
+//! }
+//! material gold { type metal albedo 0.8 0.6 0.2 fuzz 0.1 }
+//! material copper {
+//!     type conductor       # a rough metal with the GGX microfacet model
+//!     preset copper        # gold, copper, silver or aluminum ...
+//!     eta 0.2 0.92 1.1     # ... and/or a complex index of refraction per channel
+//!     k 3.9 2.45 2.14
+//!     roughness 0.3        # 0 (mirror) to 1, or `roughness u v` for brushed metal
+//! }
+//! material glass { type dielectric ior 1.5 }
+//! material frosted {
+//!     type dielectric
+//!     ior 1.5
+//!     roughness 0.3        # or a `roughness <texture>` for glass frosted in places
+//! }
+//! material lamp {
+//!     type diffuse_light   # emits `emit r g b` or a `texture <name>`
+//!     emit 4 4 4
+//!     one_sided            # optional, only the front face emits
+//! }
+//! material fog {
+//!     type isotropic       # scatters evenly in every direction inside a medium
+//!     albedo 1 1 1         # or a `texture <name>`
+//! }
+//! material cloud {
+//!     type henyey_greenstein # scatters mostly forward or backward inside a medium
+//!     albedo 0.9 0.9 0.9   # or a `texture <name>`
+//!     g 0.7                # in (-1, 1): > 0 forward, < 0 backward, 0 isotropic
+//! }
+//!
+//! sphere {
+//!     center 0 -1000 0
+//!     center2 0 -999 0     # optional, makes the sphere move during the shutter
+//!     radius 1000
+//!     material floor
+//! }
+//!
+//! quad {
+//!     corner -2 -2 0       # one corner and the two edges leaving it
+//!     u 4 0 0
+//!     v 0 4 0
+//!     material floor
+//! }
+//! box { min 0 0 0 max 1 2 1 material floor }   # six quads between two corners
+//! box { min 0 0 0 max 1 1 1 material fog density 0.5 }
+//! volume {
+//!     grid smoke.txt       # a voxel grid filling its own bounds, see `voxel_grid`
+//!     texture stone        # or a texture's red channel, clamped to [0, 1] ...
+//!     min -1 0 -1          # ... filling the box between `min` and `max`
+//!     max 1 2 1
+//!     density 2            # multiplier for the grid or texture values
+//!     material fog         # isotropic or henyey_greenstein
+//!     emission 8 3 1       # optional, glow where the density peaks, for fire
+//! }
+//! triangle { a 0 0 0 b 1 0 0 c 0 1 0 material floor }
+//! mesh {
+//!     file teapot.obj      # Wavefront OBJ, materials come from its MTL libraries
+//!     material gold        # optional, replaces every material of the mesh
+//! }
+//! ```
+//!
+//! Spheres and boxes with a `density` are filled with a medium of that density
+//! per unit length instead of having a surface, their material must be
+//! `isotropic` or `henyey_greenstein`. Every shape also takes `translate x y z`,
+//! `rotate x y z` (degrees about each axis) and `scale s` or `scale x y z`,
+//! applied in the order written.
+//! Every camera property is optional and falls back to the camera defaults.
+//! Textures and materials must be defined before they are referenced by name.
+//! File names are relative to the directory containing the scene file.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use glam::{Affine3A, EulerRot, Quat};
+
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hittable::{HittableList, HittableObject};
+use crate::image::Image;
+use crate::integrator;
+use crate::material::{ComplexIor, Material, PhaseFunction};
+use crate::obj;
+use crate::random::Sampler;
+use crate::ray::{Direction, Point};
+use crate::texture::{Filter, NoiseKind, Texture, Wrap};
+use crate::voxel_grid::VoxelGrid;
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+/// What follows a material's `roughness` key.
+enum Roughness {
+    Uniform(f32),
+    Anisotropic(f32, f32), // Along and across the world x axis
+    Texture(Texture),      // The red channel
+}
+
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut start = None;
+        for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+            let is_brace = c == '{' || c == '}';
+            if c.is_whitespace() || is_brace {
+                if let Some(s) = start.take() {
+                    tokens.push(Token {
+                        text: &line[s..i],
+                        line: line_index + 1,
+                        column: s + 1,
+                    });
+                }
+                if is_brace {
+                    tokens.push(Token {
+                        text: &line[i..=i],
+                        line: line_index + 1,
+                        column: i + 1,
+                    });
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+    }
+    tokens
+}
+
+/// Parses a scene description into its objects and the camera looking at them.
+/// Files named in the scene are looked up relative to `base_dir`.
+pub fn parse(source: &str, base_dir: &Path) -> Result<(HittableList, Camera), ParseError> {
+    let tokens = tokenize(source);
+    let mut parser = Parser {
+        end: Token {
+            text: "",
+            line: source.lines().count().max(1),
+            column: source.lines().last().map_or(0, str::len) + 1,
+        },
+        tokens: &tokens,
+        pos: 0,
+        base_dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        world: HittableList::default(),
+        camera: Camera::default(),
+    };
+    while parser.pos < tokens.len() {
+        parser.item()?;
+    }
+    Ok((parser.world, parser.camera))
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    pos: usize,
+    end: Token<'a>,
+    base_dir: &'a Path,
+    textures: HashMap<&'a str, Texture>,
+    materials: HashMap<&'a str, Material>,
+    world: HittableList,
+    camera: Camera,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(token: Token, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: token.line,
+            column: token.column,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Token<'a> {
+        self.tokens.get(self.pos).copied().unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, ParseError> {
+        let token = self.peek();
+        if token.text.is_empty() {
+            return Self::error(token, "unexpected end of file");
+        }
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), ParseError> {
+        let token = self.next()?;
+        if token.text == text {
+            Ok(())
+        } else {
+            Self::error(token, format!("expected '{text}', found '{}'", token.text))
+        }
+    }
+
+    /// Returns the next key inside a block, or `None` once the closing brace is consumed.
+    fn key(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        let token = self.next()?;
+        match token.text {
+            "}" => Ok(None),
+            "{" => Self::error(token, "expected a property name, found '{'"),
+            _ => Ok(Some(token)),
+        }
+    }
+
+    fn name(&mut self) -> Result<Token<'a>, ParseError> {
+        let token = self.next()?;
+        if token.text == "{" || token.text == "}" || token.text.parse::<f32>().is_ok() {
+            return Self::error(token, format!("expected a name, found '{}'", token.text));
+        }
+        Ok(token)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, ParseError> {
+        let token = self.next()?;
+        token
+            .text
+            .parse()
+            .or_else(|_| Self::error(token, format!("expected a number, found '{}'", token.text)))
+    }
+
+    /// A number after `key` that must be greater than zero.
+    fn positive<T: std::str::FromStr + Default + PartialOrd>(
+        &mut self,
+        key: Token,
+    ) -> Result<T, ParseError> {
+        let n: T = self.number()?;
+        if n > T::default() {
+            Ok(n)
+        } else {
+            Self::error(key, format!("'{}' must be greater than zero", key.text))
+        }
+    }
+
+    fn vector(&mut self) -> Result<Point, ParseError> {
+        Ok(Point::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn aspect(&mut self) -> Result<f32, ParseError> {
+        let token = self.next()?;
+        let ratio = match token.text.split_once(':') {
+            Some((w, h)) => w
+                .parse::<f32>()
+                .and_then(|w| h.parse::<f32>().map(|h| w / h)),
+            None => token.text.parse(),
+        };
+        match ratio {
+            Ok(ratio) if ratio.is_finite() && ratio > 0.0 => Ok(ratio),
+            _ => Self::error(token, format!("invalid aspect ratio '{}'", token.text)),
+        }
+    }
+
+    fn is_number_next(&self) -> bool {
+        self.peek().text.parse::<f32>().is_ok()
+    }
+
+    /// A color written inline, or the name of a texture defined earlier.
+    fn texture_or_color(&mut self) -> Result<Texture, ParseError> {
+        if self.is_number_next() {
+            Ok(Texture::solid_color(self.vector()?))
+        } else {
+            self.texture_reference()
+        }
+    }
+
+    fn texture_reference(&mut self) -> Result<Texture, ParseError> {
+        let token = self.name()?;
+        self.textures.get(token.text).map_or_else(
+            || Self::error(token, format!("unknown texture '{}'", token.text)),
+            |texture| Ok(texture.clone()),
+        )
+    }
+
+    fn material_reference(&mut self) -> Result<Material, ParseError> {
+        let token = self.name()?;
+        self.materials.get(token.text).map_or_else(
+            || Self::error(token, format!("unknown material '{}'", token.text)),
+            |material| Ok(material.clone()),
+        )
+    }
+
+    fn item(&mut self) -> Result<(), ParseError> {
+        let token = self.next()?;
+        match token.text {
+            "camera" => self.camera_block(),
+            "background" => self.background_block(token),
+            "texture" => {
+                let name = self.name()?;
+                if self.textures.contains_key(name.text) {
+                    return Self::error(
+                        name,
+                        format!("texture '{}' is already defined", name.text),
+                    );
+                }
+                let texture = self.texture_block(token)?;
+                self.textures.insert(name.text, texture);
+                Ok(())
+            }
+            "material" => {
+                let name = self.name()?;
+                if self.materials.contains_key(name.text) {
+                    return Self::error(
+                        name,
+                        format!("material '{}' is already defined", name.text),
+                    );
+                }
+                let material = self.material_block(token)?;
+                self.materials.insert(name.text, material);
+                Ok(())
+            }
+            "sphere" => self.sphere_block(token),
+            "quad" => self.quad_block(token),
+            "box" => self.box_block(token),
+            "triangle" => self.triangle_block(token),
+            "mesh" => self.mesh_block(token),
+            "volume" => self.volume_block(token),
+            _ => Self::error(token, format!("unknown block '{}'", token.text)),
+        }
+    }
+
+    fn camera_block(&mut self) -> Result<(), ParseError> {
+        self.expect("{")?;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "width" => self.camera.image_width = self.positive(key)?,
+                "aspect" => self.camera.aspect_ratio = self.aspect()?,
+                "spp" => self.camera.samples_per_pixel = self.positive(key)?,
+                "max_depth" => self.camera.max_depth = self.positive(key)?,
+                "roulette_depth" => self.camera.roulette_depth = self.number()?,
+                "integrator" => {
+                    let name = self.name()?;
+                    let Some(integrator) = integrator::find(name.text) else {
+                        return Self::error(
+                            name,
+                            format!(
+                                "unknown integrator '{}', available integrators: {}",
+                                name.text,
+                                integrator::names().join(", ")
+                            ),
+                        );
+                    };
+                    self.camera.integrator = integrator;
+                }
+                "vfov" => self.camera.vfov = self.number()?,
+                "look_from" => self.camera.look_from = self.vector()?,
+                "look_at" => self.camera.look_at = self.vector()?,
+                "vup" => self.camera.vup = self.vector()?,
+                "defocus_angle" => self.camera.defocus_angle = self.number()?,
+                "focus_dist" => self.camera.focus_dist = self.number()?,
+                _ => return Self::error(key, format!("unknown camera property '{}'", key.text)),
+            }
+        }
+        Ok(())
+    }
+
+    fn background_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut kind = None;
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut bottom = Color::new(1.0, 1.0, 1.0);
+        let mut top = Color::new(0.5, 0.7, 1.0);
+        let mut image = None;
+        let mut rotation = 0.0;
+        let mut intensity = 1.0;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "type" => kind = Some(self.name()?),
+                "color" => color = self.vector()?,
+                "bottom" => bottom = self.vector()?,
+                "top" => top = self.vector()?,
+                "file" => {
+                    let file = self.next()?;
+                    image = Some(self.load_image(file, false)?);
+                }
+                "rotation" => rotation = self.number()?,
+                "intensity" => intensity = self.number()?,
+                _ => {
+                    return Self::error(key, format!("unknown background property '{}'", key.text))
+                }
+            }
+        }
+        self.camera.background = match (kind.map(|t| (t, t.text)), image) {
+            (Some((_, "solid")), _) => Background::solid(color),
+            (Some((_, "gradient")), _) => Background::gradient(bottom, top),
+            (Some((_, "environment")), Some(image)) => {
+                Background::environment(image, rotation, intensity)
+            }
+            (Some((t, "environment")), None) => {
+                return Self::error(t, "environment background is missing its 'file'")
+            }
+            (Some((t, other)), _) => {
+                return Self::error(t, format!("unknown background type '{other}'"))
+            }
+            (None, _) => return Self::error(block, "background is missing its 'type'"),
+        };
+        Ok(())
+    }
+
+    /// Loads the image named by `token`. `linear` skips the sRGB decoding of 8/16-bit files.
+    fn load_image(&self, token: Token, linear: bool) -> Result<Image, ParseError> {
+        let path = self.base_dir.join(token.text);
+        let image = if linear {
+            Image::load_linear(&path)
+        } else {
+            Image::load(&path)
+        };
+        image.or_else(|e| {
+            Self::error(
+                token,
+                format!("cannot load image '{}': {e}", path.display()),
+            )
+        })
+    }
+
+    fn texture_block(&mut self, block: Token) -> Result<Texture, ParseError> {
+        self.expect("{")?;
+        let mut kind = None;
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut scale = 1.0;
+        let mut even = Texture::solid_color(Color::new(0.0, 0.0, 0.0));
+        let mut odd = Texture::solid_color(Color::new(1.0, 1.0, 1.0));
+        let mut mode = None;
+        let mut octaves = 7;
+        let mut seed = 0;
+        let mut file = None;
+        let mut filter = None;
+        let mut wrap = None;
+        let mut linear = false;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "type" => kind = Some(self.name()?),
+                "color" => color = self.vector()?,
+                "scale" => scale = self.number()?,
+                "mode" => mode = Some(self.name()?),
+                "octaves" => octaves = self.number()?,
+                "seed" => seed = self.number()?,
+                "file" => file = Some(self.next()?),
+                "filter" => filter = Some(self.name()?),
+                "wrap" => wrap = Some(self.name()?),
+                "linear" => linear = true,
+                "even" => even = self.texture_or_color()?,
+                "odd" => odd = self.texture_or_color()?,
+                _ => return Self::error(key, format!("unknown texture property '{}'", key.text)),
+            }
+        }
+        match kind.map(|t| (t, t.text)) {
+            Some((_, "solid")) => Ok(Texture::solid_color(color)),
+            Some((_, "checker")) => Ok(Texture::checker_with_textures(scale, even, odd)),
+            Some((_, "noise")) => {
+                let noise_kind = match mode.map(|t| (t, t.text)) {
+                    Some((_, "smooth")) | None => NoiseKind::Smooth,
+                    Some((_, "turbulence")) => NoiseKind::Turbulence,
+                    Some((_, "marble")) => NoiseKind::Marble,
+                    Some((t, other)) => {
+                        return Self::error(t, format!("unknown noise mode '{other}'"))
+                    }
+                };
+                let mut rng = Sampler::new(seed);
+                Ok(Texture::noise(noise_kind, scale, octaves, &mut rng))
+            }
+            Some((t, "image")) => {
+                let Some(file) = file else {
+                    return Self::error(t, "image texture is missing its 'file'");
+                };
+                let filter = match filter.map(|t| (t, t.text)) {
+                    Some((_, "bilinear")) | None => Filter::Bilinear,
+                    Some((_, "nearest")) => Filter::Nearest,
+                    Some((t, other)) => return Self::error(t, format!("unknown filter '{other}'")),
+                };
+                let wrap = match wrap.map(|t| (t, t.text)) {
+                    Some((_, "repeat")) | None => Wrap::Repeat,
+                    Some((_, "clamp")) => Wrap::Clamp,
+                    Some((_, "mirror")) => Wrap::Mirror,
+                    Some((t, other)) => {
+                        return Self::error(t, format!("unknown wrap mode '{other}'"))
+                    }
+                };
+                let image = self.load_image(file, linear)?;
+                Ok(Texture::image(Arc::new(image), filter, wrap))
+            }
+            Some((t, other)) => Self::error(t, format!("unknown texture type '{other}'")),
+            None => Self::error(block, "texture is missing its 'type'"),
+        }
+    }
+
+    fn material_block(&mut self, block: Token) -> Result<Material, ParseError> {
+        self.expect("{")?;
+        let mut kind = None;
+        let mut albedo = Color::new(0.5, 0.5, 0.5);
+        let mut texture = None;
+        let mut fuzz = 0.0;
+        let mut ior = 1.5;
+        let mut emit = Color::new(1.0, 1.0, 1.0);
+        let mut one_sided = false;
+        let mut g = 0.0;
+        let mut preset = None;
+        let mut eta = None;
+        let mut k = None;
+        let mut roughness = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "type" => kind = Some(self.name()?),
+                "albedo" => albedo = self.vector()?,
+                "texture" => texture = Some((key, self.texture_reference()?)),
+                "fuzz" => fuzz = self.number()?,
+                "ior" => ior = self.number()?,
+                "emit" => emit = self.vector()?,
+                "one_sided" => one_sided = true,
+                "preset" => preset = Some(self.metal_preset()?),
+                "eta" => eta = Some(self.vector()?),
+                "k" => k = Some(self.vector()?),
+                "roughness" => roughness = Some((key, self.roughness(key)?)),
+                "g" => {
+                    g = self.number()?;
+                    if !(-1.0 < g && g < 1.0) {
+                        return Self::error(key, "'g' must be between -1 and 1");
+                    }
+                }
+                _ => return Self::error(key, format!("unknown material property '{}'", key.text)),
+            }
+        }
+        match (kind.map(|t| t.text), texture) {
+            (Some("lambertian"), Some((_, texture))) => {
+                Ok(Material::lambertian_with_texture(texture))
+            }
+            (Some("lambertian"), None) => Ok(Material::lambertian(albedo)),
+            (Some("diffuse_light"), Some((_, texture))) => {
+                Ok(Material::diffuse_light_with_texture(texture, one_sided))
+            }
+            (Some("diffuse_light"), None) if one_sided => Ok(Material::diffuse_light_with_texture(
+                Texture::solid_color(emit),
+                true,
+            )),
+            (Some("diffuse_light"), None) => Ok(Material::diffuse_light(emit)),
+            (Some("isotropic"), Some((_, texture))) => Ok(Material::isotropic(texture)),
+            (Some("isotropic"), None) => Ok(Material::isotropic(Texture::solid_color(albedo))),
+            (Some("henyey_greenstein"), Some((_, texture))) => Ok(Material::volume(
+                texture,
+                PhaseFunction::HenyeyGreenstein(g),
+            )),
+            (Some("henyey_greenstein"), None) => Ok(Material::volume(
+                Texture::solid_color(albedo),
+                PhaseFunction::HenyeyGreenstein(g),
+            )),
+            (Some("conductor"), None) => {
+                let ior = Self::complex_ior(block, preset, eta, k)?;
+                match roughness {
+                    None => Ok(Material::conductor(ior, 0.0, 0.0)),
+                    Some((_, Roughness::Uniform(r))) => Ok(Material::conductor(ior, r, r)),
+                    Some((_, Roughness::Anisotropic(u, v))) => Ok(Material::conductor(ior, u, v)),
+                    Some((key, Roughness::Texture(_))) => {
+                        Self::error(key, "conductor roughness must be one or two numbers")
+                    }
+                }
+            }
+            (Some("metal" | "conductor" | "dielectric"), Some((key, _))) => Self::error(
+                key,
+                "only lambertian, diffuse_light and volume materials take a texture",
+            ),
+            (Some("metal"), None) => Ok(Material::metal(albedo, fuzz)),
+            (Some("dielectric"), None) => match roughness {
+                None => Ok(Material::dielectric(ior)),
+                Some((_, Roughness::Uniform(r))) => Ok(Material::rough_dielectric(
+                    ior,
+                    Texture::solid_color(Color::splat(r)),
+                )),
+                Some((_, Roughness::Texture(texture))) => {
+                    Ok(Material::rough_dielectric(ior, texture))
+                }
+                Some((key, Roughness::Anisotropic(..))) => {
+                    Self::error(key, "dielectric roughness must be a number or a texture")
+                }
+            },
+            (Some(other), _) => Self::error(
+                kind.unwrap_or(block),
+                format!("unknown material type '{other}'"),
+            ),
+            (None, _) => Self::error(block, "material is missing its 'type'"),
+        }
+    }
+
+    fn metal_preset(&mut self) -> Result<ComplexIor, ParseError> {
+        let name = self.name()?;
+        ComplexIor::preset(name.text).map_or_else(
+            || Self::error(name, format!("unknown metal preset '{}'", name.text)),
+            Ok,
+        )
+    }
+
+    /// Reads `r`, `u v` or the name of a texture after the `roughness` key.
+    fn roughness(&mut self, key: Token) -> Result<Roughness, ParseError> {
+        if !self.is_number_next() {
+            return Ok(Roughness::Texture(self.texture_reference()?));
+        }
+        let u: f32 = self.number()?;
+        let v = if self.is_number_next() {
+            Some(self.number()?)
+        } else {
+            None
+        };
+        if !(0.0..=1.0).contains(&u) || v.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+            return Self::error(key, "'roughness' must be between 0 and 1");
+        }
+        Ok(v.map_or(Roughness::Uniform(u), |v| Roughness::Anisotropic(u, v)))
+    }
+
+    /// A conductor's index of refraction: `eta` and `k` if given, each
+    /// falling back to the preset's.
+    fn complex_ior(
+        block: Token,
+        preset: Option<ComplexIor>,
+        eta: Option<Color>,
+        k: Option<Color>,
+    ) -> Result<ComplexIor, ParseError> {
+        match (preset, eta, k) {
+            (_, Some(eta), Some(k)) => Ok(ComplexIor::new(eta, k)),
+            (Some(preset), eta, k) => Ok(ComplexIor::new(
+                eta.unwrap_or(preset.eta),
+                k.unwrap_or(preset.k),
+            )),
+            (None, _, _) => Self::error(block, "conductor needs a 'preset' or both 'eta' and 'k'"),
+        }
+    }
+
+    /// Reads `translate`, `rotate` or `scale` into `transform`, returns false for any other key.
+    fn transform(&mut self, key: Token, transform: &mut Affine3A) -> Result<bool, ParseError> {
+        let step = match key.text {
+            "translate" => Affine3A::from_translation(self.vector()?),
+            "rotate" => {
+                let degrees = self.vector()?;
+                let rotation = Quat::from_euler(
+                    EulerRot::XYZ,
+                    degrees.x.to_radians(),
+                    degrees.y.to_radians(),
+                    degrees.z.to_radians(),
+                );
+                Affine3A::from_quat(rotation)
+            }
+            "scale" => {
+                let token = self.peek();
+                let x: f32 = self.number()?;
+                let scale = if self.is_number_next() {
+                    Direction::new(x, self.number()?, self.number()?)
+                } else {
+                    Direction::splat(x)
+                };
+                if scale.cmpeq(Direction::ZERO).any() {
+                    return Self::error(token, "scale must not be zero");
+                }
+                Affine3A::from_scale(scale)
+            }
+            _ => return Ok(false),
+        };
+        *transform = step * *transform;
+        Ok(true)
+    }
+
+    fn place(&mut self, object: HittableObject, transform: Affine3A) {
+        if transform == Affine3A::IDENTITY {
+            self.world.add(object);
+        } else {
+            self.world
+                .add(HittableObject::instance(Arc::new(object), transform));
+        }
+    }
+
+    fn sphere_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut center = Point::new(0.0, 0.0, 0.0);
+        let mut center2 = None;
+        let mut radius = 1.0;
+        let mut material = None;
+        let mut density = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "center" => center = self.vector()?,
+                "center2" => center2 = Some(self.vector()?),
+                "radius" => radius = self.number()?,
+                "material" => material = Some(self.material_reference()?),
+                "density" => density = Some((key, self.number()?)),
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown sphere property '{}'", key.text)),
+            }
+        }
+        let Some(material) = material else {
+            return Self::error(block, "sphere is missing its 'material'");
+        };
+        let surface = material.clone();
+        let sphere = match center2 {
+            Some(center2) => HittableObject::moving_sphere(center, center2, radius, surface),
+            None => HittableObject::sphere(center, radius, surface),
+        };
+        let sphere = Self::medium(sphere, density, material)?;
+        self.place(sphere, transform);
+        Ok(())
+    }
+
+    fn quad_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut corner = Point::new(0.0, 0.0, 0.0);
+        let mut u = Direction::new(1.0, 0.0, 0.0);
+        let mut v = Direction::new(0.0, 1.0, 0.0);
+        let mut material = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "corner" => corner = self.vector()?,
+                "u" => u = self.vector()?,
+                "v" => v = self.vector()?,
+                "material" => material = Some(self.material_reference()?),
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown quad property '{}'", key.text)),
+            }
+        }
+        let Some(material) = material else {
+            return Self::error(block, "quad is missing its 'material'");
+        };
+        if u.cross(v).length_squared() == 0.0 {
+            return Self::error(block, "quad edges 'u' and 'v' must not be parallel");
+        }
+        self.place(HittableObject::quad(corner, u, v, material), transform);
+        Ok(())
+    }
+
+    fn box_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut min = Point::new(0.0, 0.0, 0.0);
+        let mut max = Point::new(1.0, 1.0, 1.0);
+        let mut material = None;
+        let mut density = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "min" => min = self.vector()?,
+                "max" => max = self.vector()?,
+                "material" => material = Some(self.material_reference()?),
+                "density" => density = Some((key, self.number()?)),
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown box property '{}'", key.text)),
+            }
+        }
+        let Some(material) = material else {
+            return Self::error(block, "box is missing its 'material'");
+        };
+        let sides = HittableObject::cuboid(min, max, &material);
+        if density.is_some() {
+            let sides = HittableObject::bvh(sides.objects);
+            let medium = Self::medium(sides, density, material)?;
+            self.place(medium, transform);
+        } else if transform == Affine3A::IDENTITY {
+            self.world.append(sides);
+        } else {
+            let sides = HittableObject::bvh(sides.objects);
+            self.place(sides, transform);
+        }
+        Ok(())
+    }
+
+    /// Fills `boundary` with a medium when the shape was given a `density`.
+    fn medium(
+        boundary: HittableObject,
+        density: Option<(Token, f32)>,
+        material: Material,
+    ) -> Result<HittableObject, ParseError> {
+        match density {
+            None => Ok(boundary),
+            Some((key, density)) if density <= 0.0 => {
+                Self::error(key, "'density' must be greater than zero")
+            }
+            Some((key, _)) if !material.is_volumetric() => Self::error(
+                key,
+                "a shape with a 'density' needs an isotropic or henyey_greenstein material",
+            ),
+            Some((_, density)) => Ok(HittableObject::constant_medium(boundary, density, material)),
+        }
+    }
+
+    fn triangle_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut vertices = [Point::new(0.0, 0.0, 0.0); 3];
+        let mut material = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "a" => vertices[0] = self.vector()?,
+                "b" => vertices[1] = self.vector()?,
+                "c" => vertices[2] = self.vector()?,
+                "material" => material = Some(self.material_reference()?),
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown triangle property '{}'", key.text)),
+            }
+        }
+        let Some(material) = material else {
+            return Self::error(block, "triangle is missing its 'material'");
+        };
+        let [a, b, c] = vertices;
+        self.place(HittableObject::triangle(a, b, c, material), transform);
+        Ok(())
+    }
+
+    fn mesh_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut meshes = None;
+        let mut material = None;
+        while let Some(key) = self.key()? {
+            match key.text {
+                "file" => {
+                    let token = self.next()?;
+                    let path = self.base_dir.join(token.text);
+                    meshes = Some(obj::load(&path).or_else(|e| Self::error(token, e.to_string()))?);
+                }
+                "material" => material = Some(self.material_reference()?),
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown mesh property '{}'", key.text)),
+            }
+        }
+        let Some(meshes) = meshes else {
+            return Self::error(block, "mesh is missing its 'file'");
+        };
+        for mut data in meshes {
+            if let Some(material) = &material {
+                data.materials = vec![material.clone()];
+                data.material_ids.clear();
+            }
+            let mesh = HittableObject::mesh(data);
+            self.place(mesh, transform);
+        }
+        Ok(())
+    }
+
+    fn volume_block(&mut self, block: Token) -> Result<(), ParseError> {
+        self.expect("{")?;
+        let mut transform = Affine3A::IDENTITY;
+        let mut grid = None;
+        let mut texture = None;
+        let mut min = Point::new(0.0, 0.0, 0.0);
+        let mut max = Point::new(1.0, 1.0, 1.0);
+        let mut density = 1.0;
+        let mut material = None;
+        let mut emission = Color::new(0.0, 0.0, 0.0);
+        while let Some(key) = self.key()? {
+            match key.text {
+                "grid" => {
+                    let token = self.next()?;
+                    let path = self.base_dir.join(token.text);
+                    let loaded = VoxelGrid::load(&path).or_else(|e| {
+                        Self::error(
+                            token,
+                            format!("cannot load voxel grid '{}': {e}", path.display()),
+                        )
+                    })?;
+                    grid = Some(Arc::new(loaded));
+                }
+                "texture" => texture = Some(self.texture_reference()?),
+                "min" => min = self.vector()?,
+                "max" => max = self.vector()?,
+                "density" => density = self.positive(key)?,
+                "material" => material = Some((key, self.material_reference()?)),
+                "emission" => emission = self.vector()?,
+                _ if self.transform(key, &mut transform)? => {}
+                _ => return Self::error(key, format!("unknown volume property '{}'", key.text)),
+            }
+        }
+        let material = match material {
+            Some((_, material)) if material.is_volumetric() => material,
+            Some((key, _)) => {
+                return Self::error(
+                    key,
+                    "a volume needs an isotropic or henyey_greenstein material",
+                )
+            }
+            None => return Self::error(block, "volume is missing its 'material'"),
+        };
+        let volume = match (grid, texture) {
+            (Some(grid), None) => HittableObject::grid_medium(grid, density, material, emission),
+            (None, Some(texture)) => {
+                let sides = HittableObject::cuboid(min, max, &material);
+                let boundary = HittableObject::bvh(sides.objects);
+                HittableObject::texture_medium(boundary, texture, density, material, emission)
+            }
+            (Some(_), Some(_)) => {
+                return Self::error(block, "volume takes either a 'grid' or a 'texture'")
+            }
+            (None, None) => return Self::error(block, "volume is missing its 'grid' or 'texture'"),
+        };
+        self.place(volume, transform);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> ParseError {
+        match parse(source, Path::new(".")) {
+            Ok(_) => panic!("parsed {source:?}"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn tokens_split_at_whitespace_and_braces_and_skip_comments() {
+        let tokens = tokenize("camera{width 40 # ignored }\n  spp\t8}");
+        let found: Vec<_> = tokens.iter().map(|t| (t.text, t.line, t.column)).collect();
+        assert_eq!(
+            found,
+            [
+                ("camera", 1, 1),
+                ("{", 1, 7),
+                ("width", 1, 8),
+                ("40", 1, 14),
+                ("spp", 2, 3),
+                ("8", 2, 7),
+                ("}", 2, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn camera_properties_are_read() {
+        let (_, camera) = parse("camera { width 64 spp 3 max_depth 7 }", Path::new(".")).unwrap();
+        assert_eq!(camera.image_width, 64);
+        assert_eq!(camera.samples_per_pixel, 3);
+        assert_eq!(camera.max_depth, 7);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let e = parse_error("camera {\n    width 10\n    fov 20\n}");
+        assert_eq!((e.line, e.column), (3, 5));
+        assert_eq!(e.message, "unknown camera property 'fov'");
+
+        let e = parse_error("material m { type lambertian }\n  cone { }");
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.message, "unknown block 'cone'");
+
+        let e = parse_error("material m { type lambertian shiny 1 }");
+        assert_eq!(e.message, "unknown material property 'shiny'");
+
+        let e = parse_error("sphere { center 0 0 0 radius 1 material missing }");
+        assert!(e.message.contains("missing"), "{}", e.message);
+
+        let e = parse_error("camera { width 10");
+        assert_eq!(e.message, "unexpected end of file");
+    }
+
+    #[test]
+    fn zero_sizes_are_rejected() {
+        for (source, key) in [
+            ("camera { width 0 }", "width"),
+            ("camera { spp 0 }", "spp"),
+            ("camera { max_depth 0 }", "max_depth"),
+        ] {
+            let e = parse_error(source);
+            assert_eq!((e.line, e.column), (1, 10), "{source}");
+            assert_eq!(e.message, format!("'{key}' must be greater than zero"));
+        }
+    }
+
+    #[test]
+    fn volumes_need_a_positive_density() {
+        for source in ["volume { density 0 }", "volume { density -2 }"] {
+            let e = parse_error(source);
+            assert_eq!((e.line, e.column), (1, 10), "{source}");
+            assert_eq!(e.message, "'density' must be greater than zero");
+        }
+    }
+}
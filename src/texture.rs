@@ -1,12 +1,23 @@
-use crate::{color::Color, ray::Point};
+use std::sync::Arc;
+
+use crate::random::Sampler;
+use crate::{color::Color, image::Image, ray::Point};
 
 mod checker;
+mod image_texture;
+mod noise;
+mod perlin;
 mod solid_color;
 
+pub use image_texture::{Filter, Wrap};
+pub use noise::NoiseKind;
+
 #[derive(Debug, Clone)]
 pub enum Texture {
     SolidColor(solid_color::SolidColor),
     Checker(checker::Checker),
+    Image(image_texture::ImageTexture),
+    Noise(noise::Noise),
 }
 
 impl Default for Texture {
@@ -20,6 +31,8 @@ impl Texture {
         match self {
             Self::SolidColor(solid_color) => solid_color.value(u, v, p),
             Self::Checker(checker) => checker.value(u, v, p),
+            Self::Image(image) => image.value(u, v, p),
+            Self::Noise(noise) => noise.value(u, v, p),
         }
     }
     pub const fn solid_color(c: Color) -> Self {
@@ -29,4 +42,16 @@ impl Texture {
     pub fn checker(scale: f32, c1: Color, c2: Color) -> Self {
         Self::Checker(checker::Checker::new_using_color(scale, c1, c2))
     }
+    pub fn checker_with_textures(scale: f32, even: Self, odd: Self) -> Self {
+        Self::Checker(checker::Checker::new(scale, Box::new(even), Box::new(odd)))
+    }
+    pub const fn image(image: Arc<Image>, filter: Filter, wrap: Wrap) -> Self {
+        Self::Image(image_texture::ImageTexture::new(image, filter, wrap))
+    }
+    /// Perlin noise with freshly shuffled tables drawn from `rng`. `octaves`
+    /// only matters for turbulence and marble.
+    pub fn noise(kind: NoiseKind, scale: f32, octaves: u32, rng: &mut Sampler) -> Self {
+        let perlin = Arc::new(perlin::Perlin::new(rng));
+        Self::Noise(noise::Noise::new(perlin, kind, scale, octaves))
+    }
 }
@@ -23,6 +23,7 @@ impl Checker {
             Box::new(Texture::solid_color(c2)),
         )
     }
+    #[allow(clippy::cast_possible_truncation)]
     pub fn value(&self, u: f32, v: f32, p: &Point) -> Color {
         let x_int = (p.x * self.inv_scale).floor() as i32;
         let y_int = (p.y * self.inv_scale).floor() as i32;
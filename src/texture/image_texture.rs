@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::ray::Point;
+
+/// How a texture lookup between texel centers is resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,  // The texel the point falls in
+    Bilinear, // A blend of the four nearest texel centers
+}
+
+/// What happens to texture coordinates outside [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat, // Tile the image
+    Clamp,  // Stretch the edge texels outwards
+    Mirror, // Tile the image, flipping every other copy
+}
+
+impl Wrap {
+    /// Maps a texel index that may be out of range into `0..size`.
+    const fn apply(self, index: i64, size: i64) -> i64 {
+        match self {
+            Self::Repeat => index.rem_euclid(size),
+            Self::Clamp => {
+                if index < 0 {
+                    0
+                } else if index >= size {
+                    size - 1
+                } else {
+                    index
+                }
+            }
+            Self::Mirror => {
+                let folded = index.rem_euclid(2 * size);
+                if folded < size {
+                    folded
+                } else {
+                    2 * size - 1 - folded
+                }
+            }
+        }
+    }
+}
+
+/// Looks colors up in an image by the hit's texture coordinates.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    image: Arc<Image>,
+    filter: Filter,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    pub const fn new(image: Arc<Image>, filter: Filter, wrap: Wrap) -> Self {
+        Self {
+            image,
+            filter,
+            wrap,
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss
+    )]
+    pub fn value(&self, u: f32, v: f32, _p: &Point) -> Color {
+        let width = self.image.width() as i64;
+        let height = self.image.height() as i64;
+        // v runs bottom to top, image rows top to bottom
+        let column = u * width as f32;
+        let row = (1.0 - v) * height as f32;
+
+        match self.filter {
+            Filter::Nearest => self.texel(column.floor() as i64, row.floor() as i64),
+            Filter::Bilinear => {
+                // Texel centers sit at half-integer coordinates
+                let (column, row) = (column - 0.5, row - 0.5);
+                let (left, top) = (column.floor(), row.floor());
+                let (tx, ty) = (column - left, row - top);
+                let (i, j) = (left as i64, top as i64);
+                let upper = self.texel(i, j).lerp(self.texel(i + 1, j), tx);
+                let lower = self.texel(i, j + 1).lerp(self.texel(i + 1, j + 1), tx);
+                upper.lerp(lower, ty)
+            }
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss
+    )]
+    fn texel(&self, i: i64, j: i64) -> Color {
+        let width = self.image.width() as i64;
+        let height = self.image.height() as i64;
+        let x = self.wrap.apply(i, width) as usize;
+        let y = self.wrap.apply(j, height) as usize;
+        self.image.get(x, y)
+    }
+}
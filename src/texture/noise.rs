@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use super::perlin::Perlin;
+use crate::color::Color;
+use crate::ray::Point;
+
+/// How the Perlin noise is turned into a gray level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseKind {
+    Smooth,     // Plain noise, soft blobs
+    Turbulence, // Summed octaves, a net of sharp creases
+    Marble,     // Stripes along z bent by turbulence
+}
+
+#[derive(Debug, Clone)]
+pub struct Noise {
+    perlin: Arc<Perlin>,
+    kind: NoiseKind,
+    scale: f32,
+    octaves: u32,
+}
+
+impl Noise {
+    pub const fn new(perlin: Arc<Perlin>, kind: NoiseKind, scale: f32, octaves: u32) -> Self {
+        Self {
+            perlin,
+            kind,
+            scale,
+            octaves,
+        }
+    }
+
+    pub fn value(&self, _u: f32, _v: f32, p: &Point) -> Color {
+        let gray = match self.kind {
+            NoiseKind::Smooth => 0.5 * (1.0 + self.perlin.noise(self.scale * *p)),
+            NoiseKind::Turbulence => self.perlin.turbulence(self.scale * *p, self.octaves),
+            NoiseKind::Marble => {
+                let phase =
+                    10.0f32.mul_add(self.perlin.turbulence(*p, self.octaves), self.scale * p.z);
+                0.5 * (1.0 + phase.sin())
+            }
+        };
+        Color::splat(gray)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::random::{self, Sampler};
+use crate::ray::{Direction, Point};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise on the integer lattice (Perlin 1985): random unit gradients
+/// at the lattice points, picked through three shuffled permutation tables.
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    gradients: [Direction; POINT_COUNT],
+    perm_x: [u8; POINT_COUNT],
+    perm_y: [u8; POINT_COUNT],
+    perm_z: [u8; POINT_COUNT],
+}
+
+impl Perlin {
+    pub fn new(rng: &mut Sampler) -> Self {
+        Self {
+            gradients: std::array::from_fn(|_| random::uniform_sphere(rng).0),
+            perm_x: permutation(rng),
+            perm_y: permutation(rng),
+            perm_z: permutation(rng),
+        }
+    }
+
+    /// Noise in [-1, 1], zero at every lattice point.
+    pub fn noise(&self, p: Point) -> f32 {
+        let cell = p.floor();
+        let offset = p - cell;
+        let base = cell.as_ivec3();
+
+        // Hermite smoothing hides the lattice in the interpolation weights
+        let weights = offset * offset * (Point::splat(3.0) - 2.0 * offset);
+
+        (0..8u8)
+            .map(|bits| {
+                let corner = Point::new(
+                    f32::from(bits & 1),
+                    f32::from((bits >> 1) & 1),
+                    f32::from(bits >> 2),
+                );
+                let lattice = base + corner.as_ivec3();
+                let index = self.perm_x[wrap(lattice.x)]
+                    ^ self.perm_y[wrap(lattice.y)]
+                    ^ self.perm_z[wrap(lattice.z)];
+                let weight = (corner * weights + (Point::ONE - corner) * (Point::ONE - weights))
+                    .element_product();
+                weight * self.gradients[usize::from(index)].dot(offset - corner)
+            })
+            .sum()
+    }
+
+    /// Sum of `depth` octaves, each at twice the frequency and half the weight
+    /// of the one before.
+    pub fn turbulence(&self, p: Point, depth: u32) -> f32 {
+        let mut accum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        accum.abs()
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+const fn wrap(i: i32) -> usize {
+    (i & 255) as usize
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn permutation(rng: &mut Sampler) -> [u8; POINT_COUNT] {
+    let mut p: [u8; POINT_COUNT] = std::array::from_fn(|i| i as u8);
+    for i in (1..POINT_COUNT).rev() {
+        let target = random::number(rng, 0, i + 1);
+        p.swap(i, target);
+    }
+    p
+}
@@ -10,3 +10,42 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
     let r_out_parallel = n * -((1.0 - r_out_perpendicular.length_squared()).abs().sqrt());
     r_out_parallel + r_out_perpendicular
 }
+
+/// An orthonormal basis whose `w` axis is a given direction, for carrying
+/// directions sampled around +z to wherever they are needed.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// `w` must have unit length.
+    pub fn new(w: Vec3) -> Self {
+        let (u, v) = w.any_orthonormal_pair();
+        Self { u, v, w }
+    }
+
+    /// Like [`Onb::new`], with `u` along `tangent` projected onto the plane
+    /// across `w`, so anisotropic surfaces keep a consistent orientation.
+    pub fn with_tangent(w: Vec3, tangent: Vec3) -> Self {
+        let u = (tangent - w * w.dot(tangent)).normalize_or_zero();
+        if u == Vec3::ZERO {
+            return Self::new(w);
+        }
+        Self {
+            u,
+            v: w.cross(u),
+            w,
+        }
+    }
+
+    pub fn to_world(self, local: Vec3) -> Vec3 {
+        self.u * local.x + self.v * local.y + self.w * local.z
+    }
+
+    pub fn to_local(self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(self.u), world.dot(self.v), world.dot(self.w))
+    }
+}
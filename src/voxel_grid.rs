@@ -0,0 +1,213 @@
+//! Densities on a regular 3D grid, read from a simple voxel file.
+//!
+//! Both file flavors hold the same fields in the same order: the grid size
+//! `nx ny nz`, the box it spans `min_x min_y min_z max_x max_y max_z`, then
+//! `nx * ny * nz` densities with x running fastest, then y, then z.
+//!
+//! - `.txt` files write them as whitespace separated numbers, `#` starts a
+//!   comment that runs to the end of the line.
+//! - `.raw` files store them as little-endian binary: three `u32` sizes
+//!   followed by `f32` bounds and densities.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ray::Point;
+
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    size: [usize; 3],
+    min: Point,
+    max: Point,
+    densities: Vec<f32>,
+    max_density: f32,
+}
+
+impl VoxelGrid {
+    /// A grid of `size` voxels spanning the box from `min` to `max`, `None`
+    /// when the densities do not fill it or the box is empty.
+    pub fn new(size: [usize; 3], min: Point, max: Point, densities: Vec<f32>) -> Option<Self> {
+        let count = size
+            .iter()
+            .try_fold(1usize, |count, &n| count.checked_mul(n))?;
+        if count == 0 || densities.len() != count || min.cmpge(max).any() {
+            return None;
+        }
+        let max_density = densities.iter().copied().fold(0.0, f32::max);
+        Some(Self {
+            size,
+            min,
+            max,
+            densities,
+            max_density,
+        })
+    }
+
+    /// Reads a grid, choosing the flavor from the extension, see the module docs.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("txt") => read_ascii(&data),
+            Some("raw") => read_raw(&data),
+            _ => Err(invalid_data("unsupported voxel grid format")),
+        }
+    }
+
+    pub const fn min(&self) -> Point {
+        self.min
+    }
+    pub const fn max(&self) -> Point {
+        self.max
+    }
+    /// The largest density anywhere in the grid.
+    pub const fn max_density(&self) -> f32 {
+        self.max_density
+    }
+
+    /// Density at `p`, blended between the eight nearest voxel centers and
+    /// zero outside the grid's box.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn density(&self, p: Point) -> f32 {
+        if p.cmplt(self.min).any() || p.cmpgt(self.max).any() {
+            return 0.0;
+        }
+        let size = Point::new(
+            self.size[0] as f32,
+            self.size[1] as f32,
+            self.size[2] as f32,
+        );
+        // Voxel centers sit at half-integer coordinates, edge voxels stretch to the box
+        let position = (p - self.min) / (self.max - self.min) * size - 0.5;
+        let lower = position.floor();
+        let fraction = position - lower;
+        let index = |axis: usize, offset: f32| {
+            ((lower[axis] + offset).max(0.0) as usize).min(self.size[axis] - 1)
+        };
+        let voxel = |dx, dy, dz| {
+            let (x, y, z) = (index(0, dx), index(1, dy), index(2, dz));
+            self.densities[(z * self.size[1] + y) * self.size[0] + x]
+        };
+        let lerp = |a: f32, b: f32, t: f32| t.mul_add(b - a, a);
+        let front = lerp(
+            lerp(voxel(0.0, 0.0, 0.0), voxel(1.0, 0.0, 0.0), fraction.x),
+            lerp(voxel(0.0, 1.0, 0.0), voxel(1.0, 1.0, 0.0), fraction.x),
+            fraction.y,
+        );
+        let back = lerp(
+            lerp(voxel(0.0, 0.0, 1.0), voxel(1.0, 0.0, 1.0), fraction.x),
+            lerp(voxel(0.0, 1.0, 1.0), voxel(1.0, 1.0, 1.0), fraction.x),
+            fraction.y,
+        );
+        lerp(front, back, fraction.z)
+    }
+}
+
+fn read_ascii(data: &[u8]) -> io::Result<VoxelGrid> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid_data("voxel grid is not text"))?;
+    let mut numbers = text.lines().flat_map(|line| {
+        line.split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+    });
+    let mut size = [0; 3];
+    for n in &mut size {
+        *n = numbers
+            .next()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data("bad voxel grid size"))?;
+    }
+    let values = numbers
+        .map(|token| {
+            token
+                .parse::<f32>()
+                .map_err(|_| invalid_data("bad number in voxel grid"))
+        })
+        .collect::<io::Result<Vec<f32>>>()?;
+    from_fields(size, &values)
+}
+
+fn read_raw(data: &[u8]) -> io::Result<VoxelGrid> {
+    let words: Vec<[u8; 4]> = data
+        .chunks_exact(4)
+        .map(|bytes| [bytes[0], bytes[1], bytes[2], bytes[3]])
+        .collect();
+    if !data.len().is_multiple_of(4) || words.len() < 3 {
+        return Err(invalid_data("truncated voxel grid"));
+    }
+    let mut size = [0; 3];
+    for (n, &word) in size.iter_mut().zip(&words) {
+        *n = usize::try_from(u32::from_le_bytes(word))
+            .map_err(|_| invalid_data("bad voxel grid size"))?;
+    }
+    let values: Vec<f32> = words[3..]
+        .iter()
+        .map(|&word| f32::from_le_bytes(word))
+        .collect();
+    from_fields(size, &values)
+}
+
+/// Builds the grid from its size and the numbers after it: bounds, then densities.
+fn from_fields(size: [usize; 3], values: &[f32]) -> io::Result<VoxelGrid> {
+    let [min_x, min_y, min_z, max_x, max_y, max_z] = *values
+        .get(..6)
+        .and_then(|bounds| <&[f32; 6]>::try_from(bounds).ok())
+        .ok_or_else(|| invalid_data("truncated voxel grid"))?;
+    let densities = values[6..].to_vec();
+    if densities.iter().any(|&d| !(d.is_finite() && d >= 0.0)) {
+        return Err(invalid_data(
+            "voxel densities must be finite and not negative",
+        ));
+    }
+    VoxelGrid::new(
+        size,
+        Point::new(min_x, min_y, min_z),
+        Point::new(max_x, max_y, max_z),
+        densities,
+    )
+    .ok_or_else(|| invalid_data("voxel grid size, bounds and densities do not match"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_and_raw_files_hold_the_same_grid() {
+        let ascii = b"2 1 1  # size\n0 0 0 2 1 1\n1 3\n";
+        let mut raw = Vec::new();
+        for n in [2u32, 1, 1] {
+            raw.extend(n.to_le_bytes());
+        }
+        for v in [0.0f32, 0.0, 0.0, 2.0, 1.0, 1.0, 1.0, 3.0] {
+            raw.extend(v.to_le_bytes());
+        }
+        for grid in [read_ascii(ascii).unwrap(), read_raw(&raw).unwrap()] {
+            assert!((grid.max_density() - 3.0).abs() < 1e-6);
+            // Flat out to the voxel centers, then blended between them
+            assert!((grid.density(Point::new(0.2, 0.5, 0.5)) - 1.0).abs() < 1e-6);
+            assert!((grid.density(Point::new(1.0, 0.5, 0.5)) - 2.0).abs() < 1e-6);
+            assert!((grid.density(Point::new(1.9, 0.1, 0.9)) - 3.0).abs() < 1e-6);
+            assert!(grid.density(Point::new(2.1, 0.5, 0.5)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn grids_with_missing_densities_are_rejected() {
+        assert!(read_ascii(b"2 2 2 0 0 0 1 1 1 1 2 3").is_err());
+        assert!(read_ascii(b"1 1 1 1 1 1 0 0 0 1").is_err());
+    }
+}